@@ -0,0 +1,36 @@
+#[macro_use]
+extern crate criterion;
+extern crate chrono;
+extern crate cron_gate;
+
+use chrono::offset::TimeZone;
+use chrono::Local;
+use criterion::{BenchmarkId, Criterion};
+use cron_gate::expression::{Expression, DATE_FORMAT};
+
+const EXPRESSIONS: [&str; 4] = [
+    "*/5 * * * * command",
+    "30 9 * * 1-5 command",
+    "0 0 31 * * command",
+    "0 0 29 2 1 command",
+];
+
+fn next_occurrence(c: &mut Criterion) {
+    let from = Local
+        .datetime_from_str("2019/01/01 00:00", DATE_FORMAT)
+        .unwrap();
+    let mut group = c.benchmark_group("next_occurrence");
+    for text in EXPRESSIONS.iter() {
+        let e = Expression::new(text).unwrap();
+        group.bench_with_input(BenchmarkId::new("arithmetic", text), &e, |b, e| {
+            b.iter(|| e.earler_excuting_datetimes(from, 10))
+        });
+        group.bench_with_input(BenchmarkId::new("scan", text), &e, |b, e| {
+            b.iter(|| e.earler_excuting_datetimes_by_scan(from, 10))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, next_occurrence);
+criterion_main!(benches);
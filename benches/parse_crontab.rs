@@ -0,0 +1,49 @@
+#[macro_use]
+extern crate criterion;
+extern crate cron_gate;
+
+use criterion::{Criterion, Throughput};
+use cron_gate::expression::Expression;
+
+const LINES: usize = 10_000;
+
+/// Returns a crontab having various kinds of fields
+fn crontab() -> String {
+    let mut lines = vec![];
+    for i in 0..LINES {
+        let line = match i % 5 {
+            0 => format!("{} * * * * /usr/bin/job-{}", i % 60, i),
+            1 => format!("*/{} {} * * 1-5 /usr/bin/job-{}", i % 30 + 1, i % 24, i),
+            2 => format!("0,30 {}-23/2 1,15 * * /usr/bin/job-{}", i % 12, i),
+            3 => format!(
+                "{} {} {} {} * /usr/bin/job-{}",
+                i % 60,
+                i % 24,
+                i % 28 + 1,
+                i % 12 + 1,
+                i
+            ),
+            _ => format!("5-59/10 */4 * 1-6,9-12 0,6 /usr/bin/job-{}", i),
+        };
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+fn parse_crontab(c: &mut Criterion) {
+    let text = crontab();
+    let mut group = c.benchmark_group("parse_crontab");
+    group.throughput(Throughput::Elements(LINES as u64));
+    group.bench_function("10k lines", |b| {
+        b.iter(|| {
+            text.lines()
+                .map(Expression::new)
+                .filter(|e| e.is_ok())
+                .count()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse_crontab);
+criterion_main!(benches);
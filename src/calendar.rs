@@ -0,0 +1,193 @@
+//! `cal`-style month grid and hour-of-week heatmap of an expression
+//!
+//! Both count the occurrences in the month in local time without a time zone.
+
+use crate::compact::CompactExpression;
+use crate::expression::{days_in_month, Expression, MONTH_NAMES};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
+
+const WEEKDAYS: [&str; 7] = ["Su", "Mo", "Tu", "We", "Th", "Fr", "Sa"];
+
+/// Width of a day in the month grid: the date, the mark and the count
+const CELL_WIDTH: usize = 7;
+
+/// Returns the year and the month of `YYYY-MM`
+///
+/// # Examples
+///
+/// ```
+/// use cron_gate::calendar::parse_month;
+///
+/// assert_eq!(parse_month("2026-11").unwrap(), (2026, 11));
+/// assert!(parse_month("2026-13").is_err());
+/// assert!(parse_month("2026/11").is_err());
+/// ```
+pub fn parse_month(text: &str) -> Result<(i32, u32), String> {
+    let error = || format!("Invalid month '{}': should be YYYY-MM", text);
+    let mut parts = text.trim().splitn(2, '-');
+    let year = parts
+        .next()
+        .and_then(|y| y.parse::<i32>().ok())
+        .ok_or_else(error)?;
+    let month = parts
+        .next()
+        .and_then(|m| m.parse::<u32>().ok())
+        .filter(|m| (1..=12).contains(m))
+        .ok_or_else(error)?;
+    Ok((year, month))
+}
+
+/// Returns the month grid marking firing days by `*` with the number of runs
+///
+/// # Examples
+///
+/// ```
+/// use cron_gate::calendar::month;
+/// use cron_gate::expression::Expression;
+///
+/// let e = Expression::new("0 9,18 * * 1-5 command").unwrap();
+/// let grid = month(&e, 2026, 11);
+/// assert_eq!(grid.lines().nth(2).unwrap(), " 1       2*   2  3*   2  4*   2  5*   2  6*   2  7");
+/// ```
+pub fn month(expression: &Expression, year: i32, month: u32) -> String {
+    let mut counts = [0; 32];
+    for datetime in occurrences(expression, year, month) {
+        counts[datetime.day() as usize] += 1;
+    }
+
+    let title = format!("{} {}", MONTH_NAMES[(month - 1) as usize], year);
+    let width = CELL_WIDTH * 7 + 6;
+    let mut lines = vec![
+        format!("{:^width$}", title, width = width)
+            .trim_end()
+            .to_string(),
+        WEEKDAYS
+            .iter()
+            .map(|w| format!("{:>2}{:5}", w, ""))
+            .collect::<Vec<String>>()
+            .join(" ")
+            .trim_end()
+            .to_string(),
+    ];
+
+    let offset = NaiveDate::from_ymd(year, month, 1)
+        .weekday()
+        .num_days_from_sunday() as usize;
+    let mut cells = vec![" ".repeat(CELL_WIDTH); offset];
+    for date in 1..=days_in_month(year, month) {
+        cells.push(match counts[date as usize] {
+            0 => format!("{:>2}{:5}", date, ""),
+            count => format!("{:>2}*{:>4}", date, count),
+        });
+    }
+    for week in cells.chunks(7) {
+        lines.push(week.join(" ").trim_end().to_string());
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Returns the number of runs in each hour of each weekday in the month
+///
+/// # Examples
+///
+/// ```
+/// use cron_gate::calendar::heatmap;
+/// use cron_gate::expression::Expression;
+///
+/// let e = Expression::new("*/15 9 * * 1-5 command").unwrap();
+/// let grid = heatmap(&e, 2026, 11);
+/// assert_eq!(grid.lines().nth(1).unwrap(), "     Su   Mo   Tu   We   Th   Fr   Sa");
+/// assert_eq!(grid.lines().nth(11).unwrap(), "09    .   20   16   16   16   16    .");
+/// ```
+pub fn heatmap(expression: &Expression, year: i32, month: u32) -> String {
+    let mut counts = [[0; 7]; 24];
+    for datetime in occurrences(expression, year, month) {
+        let day = datetime.weekday().num_days_from_sunday() as usize;
+        counts[datetime.hour() as usize][day] += 1;
+    }
+
+    let mut lines = vec![
+        format!(
+            "Runs per hour of week in {} {}",
+            MONTH_NAMES[(month - 1) as usize],
+            year
+        ),
+        format!(
+            "  {}",
+            WEEKDAYS
+                .iter()
+                .map(|w| format!("{:>5}", w))
+                .collect::<String>()
+        ),
+    ];
+    for (hour, row) in counts.iter().enumerate() {
+        let cells: String = row
+            .iter()
+            .map(|count| match count {
+                0 => format!("{:>5}", "."),
+                count => format!("{:>5}", count),
+            })
+            .collect();
+        lines.push(format!("{:02}{}", hour, cells));
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Returns datetimes in the month by the occurrence generator
+fn occurrences(expression: &Expression, year: i32, month: u32) -> Vec<NaiveDateTime> {
+    let mut ret = vec![];
+    if !expression.is_satisfiable() {
+        return ret;
+    }
+    let compact = CompactExpression::from(expression);
+    let mut current = NaiveDate::from_ymd(year, month, 1).and_hms(0, 0, 0);
+    while let Some(next) = compact.next_from(current) {
+        if next.year() != year || next.month() != month {
+            break;
+        }
+        ret.push(next);
+        current = next + chrono::Duration::minutes(1);
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_month() {
+        let e = Expression::new("0 0 1,15 * * command").unwrap();
+        assert_eq!(
+            month(&e, 2026, 11),
+            "                     November 2026\n\
+             Su      Mo      Tu      We      Th      Fr      Sa\n\
+             \x201*   1  2       3       4       5       6       7\n\
+             \x208       9      10      11      12      13      14\n\
+             15*   1 16      17      18      19      20      21\n\
+             22      23      24      25      26      27      28\n\
+             29      30\n"
+        );
+        let e = Expression::new("0 0 30 2 * command").unwrap();
+        assert!(!month(&e, 2026, 2).contains('*'));
+    }
+
+    #[test]
+    fn test_occurrences() {
+        let e = Expression::new("* * * * * command").unwrap();
+        assert_eq!(occurrences(&e, 2026, 2).len(), 28 * 24 * 60);
+        let e = Expression::new("0 0 31 * * command").unwrap();
+        assert!(occurrences(&e, 2026, 11).is_empty());
+    }
+
+    #[test]
+    fn test_heatmap() {
+        let e = Expression::new("0 0 * * 0 command").unwrap();
+        let grid = heatmap(&e, 2026, 11);
+        assert_eq!(grid.lines().count(), 26);
+        assert_eq!(
+            grid.lines().nth(2).unwrap(),
+            "00    5    .    .    .    .    .    ."
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! Source of the current time, replaceable in tests
+//!
+//! Anything relative to now takes a `Clock` instead of calling `Local::now`,
+//! so that `FixedClock` or `ManualClock` makes it deterministic.
+
+use chrono::{DateTime, Duration, Local};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+pub trait Clock {
+    fn now(&self) -> DateTime<Local>;
+
+    /// Blocks until the datetime
+    fn sleep_until(&self, datetime: DateTime<Local>);
+}
+
+/// The clock of the system
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+
+    fn sleep_until(&self, datetime: DateTime<Local>) {
+        if let Ok(duration) = (datetime - Local::now()).to_std() {
+            thread::sleep(duration);
+        }
+    }
+}
+
+/// A clock stopped at a datetime
+///
+/// # Examples
+///
+/// ```
+/// use chrono::{Local, TimeZone};
+/// use cron_gate::clock::{Clock, FixedClock};
+/// use cron_gate::expression::Expression;
+///
+/// let clock = FixedClock(Local.ymd(2019, 5, 28).and_hms(9, 30, 0));
+/// let e = Expression::new("0 * * * * command").unwrap();
+/// assert_eq!(
+///     e.earler_excuting_datetimes(clock.now(), 1),
+///     [Local.ymd(2019, 5, 28).and_hms(10, 0, 0)]
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FixedClock(pub DateTime<Local>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Local> {
+        self.0
+    }
+
+    /// Returns at once since the time never comes
+    fn sleep_until(&self, _: DateTime<Local>) {}
+}
+
+/// A clock which moves only when it is set or sleeps
+///
+/// # Examples
+///
+/// ```
+/// use chrono::{Duration, Local, TimeZone};
+/// use cron_gate::clock::{Clock, ManualClock};
+///
+/// let clock = ManualClock::new(Local.ymd(2019, 5, 28).and_hms(9, 0, 0));
+/// clock.advance(Duration::minutes(5));
+/// assert_eq!(clock.now(), Local.ymd(2019, 5, 28).and_hms(9, 5, 0));
+/// clock.sleep_until(Local.ymd(2019, 5, 28).and_hms(10, 0, 0));
+/// assert_eq!(clock.now(), Local.ymd(2019, 5, 28).and_hms(10, 0, 0));
+/// ```
+#[derive(Debug)]
+pub struct ManualClock {
+    now: Mutex<DateTime<Local>>,
+}
+
+impl ManualClock {
+    pub fn new(now: DateTime<Local>) -> ManualClock {
+        ManualClock {
+            now: Mutex::new(now),
+        }
+    }
+
+    pub fn set(&self, now: DateTime<Local>) {
+        *self.now.lock().unwrap() = now;
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap();
+        *now = *now + duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Local> {
+        *self.now.lock().unwrap()
+    }
+
+    /// Moves the clock to the datetime at once if it is later
+    fn sleep_until(&self, datetime: DateTime<Local>) {
+        let mut now = self.now.lock().unwrap();
+        if *now < datetime {
+            *now = datetime;
+        }
+    }
+}
+
+/// Shares a clock, e.g. a `ManualClock` between a test and a `Scheduler`
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now(&self) -> DateTime<Local> {
+        (**self).now()
+    }
+
+    fn sleep_until(&self, datetime: DateTime<Local>) {
+        (**self).sleep_until(datetime)
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Box<C> {
+    fn now(&self) -> DateTime<Local> {
+        (**self).now()
+    }
+
+    fn sleep_until(&self, datetime: DateTime<Local>) {
+        (**self).sleep_until(datetime)
+    }
+}
+
+/// A clock which panics when it is read, for tests of what needs no current time
+#[cfg(test)]
+pub(crate) struct UnreadClock;
+
+#[cfg(test)]
+impl Clock for UnreadClock {
+    fn now(&self) -> DateTime<Local> {
+        panic!("The clock is read")
+    }
+
+    fn sleep_until(&self, _: DateTime<Local>) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_shared_clock() {
+        let clock = Arc::new(ManualClock::new(Local.ymd(2019, 5, 28).and_hms(9, 0, 0)));
+        let shared = clock.clone();
+        shared.sleep_until(Local.ymd(2019, 5, 28).and_hms(8, 0, 0));
+        assert_eq!(clock.now(), Local.ymd(2019, 5, 28).and_hms(9, 0, 0));
+        shared.advance(Duration::hours(1));
+        assert_eq!(clock.now(), Local.ymd(2019, 5, 28).and_hms(10, 0, 0));
+
+        let boxed: Box<dyn Clock> = Box::new(FixedClock(clock.now()));
+        boxed.sleep_until(Local.ymd(2019, 5, 29).and_hms(0, 0, 0));
+        assert_eq!(boxed.now(), Local.ymd(2019, 5, 28).and_hms(10, 0, 0));
+    }
+}
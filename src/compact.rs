@@ -0,0 +1,624 @@
+use crate::expression::{days_in_month, max_days_in_month, Expression};
+use chrono::naive::{MAX_DATE, MIN_DATE};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
+
+/// Years to look ahead for the next occurrence: a whole Gregorian cycle
+pub const SEARCH_YEARS: i32 = 400;
+
+/// Compact representation of an Expression as bit masks
+///
+/// Bit `n` of each mask is set when the field accepts the value `n`.
+/// Sunday is always bit 0 of `day` (7 in the expression is folded into it),
+/// and the command is not kept.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct CompactExpression {
+    pub minute: u64,
+    pub hour: u32,
+    pub date: u32,
+    pub month: u16,
+    pub day: u8,
+}
+
+impl CompactExpression {
+    /// Returns a CompactExpression
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cron_gate::compact::CompactExpression;
+    ///
+    /// let c = CompactExpression::new("0,30 9 1 */6 1-5 command").unwrap();
+    /// assert_eq!(c, CompactExpression {
+    ///   minute: 1 | 1 << 30,
+    ///   hour: 1 << 9,
+    ///   date: 1 << 1,
+    ///   month: 1 << 1 | 1 << 7,
+    ///   day: 0b0111110,
+    /// });
+    /// ```
+    pub fn new(expression_str: &str) -> Result<CompactExpression, String> {
+        Expression::new(expression_str).map(|e| CompactExpression::from(&e))
+    }
+
+    pub fn has_minute(&self, minute: u32) -> bool {
+        has(self.minute, minute)
+    }
+
+    pub fn has_hour(&self, hour: u32) -> bool {
+        has(u64::from(self.hour), hour)
+    }
+
+    pub fn has_date(&self, date: u32) -> bool {
+        has(u64::from(self.date), date)
+    }
+
+    pub fn has_month(&self, month: u32) -> bool {
+        has(u64::from(self.month), month)
+    }
+
+    /// Sunday can be either 0 or 7
+    pub fn has_day(&self, day: u32) -> bool {
+        has(u64::from(self.day), day % 7)
+    }
+
+    /// Returns the smallest minute equal to or greater than from
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cron_gate::compact::CompactExpression;
+    ///
+    /// let c = CompactExpression::new("*/20 * * * * command").unwrap();
+    /// assert_eq!(c.next_minute(0), Some(0));
+    /// assert_eq!(c.next_minute(1), Some(20));
+    /// assert_eq!(c.next_minute(41), None);
+    /// ```
+    pub fn next_minute(&self, from: u32) -> Option<u32> {
+        next_in_mask(self.minute, from)
+    }
+
+    pub fn next_hour(&self, from: u32) -> Option<u32> {
+        next_in_mask(u64::from(self.hour), from)
+    }
+
+    pub fn next_date(&self, from: u32) -> Option<u32> {
+        next_in_mask(u64::from(self.date), from)
+    }
+
+    pub fn next_month(&self, from: u32) -> Option<u32> {
+        next_in_mask(u64::from(self.month), from)
+    }
+
+    /// Returns the largest minute equal to or less than from
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cron_gate::compact::CompactExpression;
+    ///
+    /// let c = CompactExpression::new("10-40/20 * * * * command").unwrap();
+    /// assert_eq!(c.prev_minute(59), Some(30));
+    /// assert_eq!(c.prev_minute(10), Some(10));
+    /// assert_eq!(c.prev_minute(9), None);
+    /// ```
+    pub fn prev_minute(&self, from: u32) -> Option<u32> {
+        prev_in_mask(self.minute, from)
+    }
+
+    pub fn prev_hour(&self, from: u32) -> Option<u32> {
+        prev_in_mask(u64::from(self.hour), from)
+    }
+
+    pub fn prev_date(&self, from: u32) -> Option<u32> {
+        prev_in_mask(u64::from(self.date), from)
+    }
+
+    pub fn prev_month(&self, from: u32) -> Option<u32> {
+        prev_in_mask(u64::from(self.month), from)
+    }
+
+    /// Returns true if the expression fires at least once
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cron_gate::compact::CompactExpression;
+    ///
+    /// assert!(CompactExpression::new("0 0 29 2 * command").unwrap().is_satisfiable());
+    /// assert!(!CompactExpression::new("0 0 30 2 * command").unwrap().is_satisfiable());
+    /// ```
+    pub fn is_satisfiable(&self) -> bool {
+        if self.minute == 0 || self.hour == 0 || self.day == 0 {
+            return false;
+        }
+        match self.next_date(1) {
+            Some(smallest_date) => {
+                (1..13).any(|m| self.has_month(m) && smallest_date <= max_days_in_month(m))
+            }
+            None => false,
+        }
+    }
+
+    /// Returns true if the datetime matches the expression
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use cron_gate::compact::CompactExpression;
+    ///
+    /// let c = CompactExpression::new("30 9 * * 1-5 command").unwrap();
+    /// assert!(c.matches(NaiveDate::from_ymd(2019, 5, 28).and_hms(9, 30, 0)));
+    /// assert!(!c.matches(NaiveDate::from_ymd(2019, 5, 26).and_hms(9, 30, 0)));
+    /// ```
+    pub fn matches(&self, datetime: NaiveDateTime) -> bool {
+        self.has_minute(datetime.minute())
+            && self.has_hour(datetime.hour())
+            && self.has_date(datetime.day())
+            && self.has_month(datetime.month())
+            && self.has_day(datetime.weekday().num_days_from_sunday())
+    }
+
+    /// Returns true if the expression fires on the date at least once
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use cron_gate::compact::CompactExpression;
+    ///
+    /// let c = CompactExpression::new("30 9 * * 1-5 command").unwrap();
+    /// assert!(c.fires_on(NaiveDate::from_ymd(2019, 5, 28)));
+    /// assert!(!c.fires_on(NaiveDate::from_ymd(2019, 5, 26)));
+    /// ```
+    pub fn fires_on(&self, date: NaiveDate) -> bool {
+        self.minute != 0
+            && self.hour != 0
+            && self.has_date(date.day())
+            && self.has_month(date.month())
+            && self.has_day(date.weekday().num_days_from_sunday())
+    }
+
+    /// Returns the earliest matching datetime at or after from (seconds are ignored)
+    ///
+    /// Each field is advanced to its next matching value, carrying into the upper one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use cron_gate::compact::CompactExpression;
+    ///
+    /// let c = CompactExpression::new("0 0 29 2 1 command").unwrap();
+    /// assert_eq!(
+    ///     c.next_from(NaiveDate::from_ymd(2019, 1, 1).and_hms(0, 0, 0)),
+    ///     Some(NaiveDate::from_ymd(2044, 2, 29).and_hms(0, 0, 0))
+    /// );
+    /// ```
+    pub fn next_from(&self, from: NaiveDateTime) -> Option<NaiveDateTime> {
+        let limit_year = (from.year() + SEARCH_YEARS).min(MAX_DATE.year());
+        let mut year = from.year();
+        let mut month = from.month();
+        let mut date = from.day();
+        let mut hour = from.hour();
+        let mut minute = from.minute();
+
+        while year <= limit_year {
+            match self.next_month(month) {
+                Some(m) if m == month => {}
+                Some(m) => {
+                    month = m;
+                    date = 1;
+                    hour = 0;
+                    minute = 0;
+                }
+                None => {
+                    year += 1;
+                    month = 1;
+                    date = 1;
+                    hour = 0;
+                    minute = 0;
+                    continue;
+                }
+            }
+
+            match self.next_date_in_month(year, month, date) {
+                Some(d) if d == date => {}
+                Some(d) => {
+                    date = d;
+                    hour = 0;
+                    minute = 0;
+                }
+                None => {
+                    month += 1;
+                    date = 1;
+                    hour = 0;
+                    minute = 0;
+                    if month > 12 {
+                        year += 1;
+                        month = 1;
+                    }
+                    continue;
+                }
+            }
+
+            match self.next_hour(hour) {
+                Some(h) if h == hour => {}
+                Some(h) => {
+                    hour = h;
+                    minute = 0;
+                }
+                None => {
+                    date += 1;
+                    hour = 0;
+                    minute = 0;
+                    continue;
+                }
+            }
+
+            match self.next_minute(minute) {
+                Some(m) => {
+                    return NaiveDate::from_ymd_opt(year, month, date)
+                        .and_then(|d| d.and_hms_opt(hour, m, 0));
+                }
+                None => {
+                    hour += 1;
+                    minute = 0;
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns the latest matching datetime at or before from (seconds are ignored)
+    ///
+    /// This is `next_from` going backwards, borrowing from the upper field.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use cron_gate::compact::CompactExpression;
+    ///
+    /// let c = CompactExpression::new("0 0 29 2 1 command").unwrap();
+    /// assert_eq!(
+    ///     c.prev_from(NaiveDate::from_ymd(2019, 1, 1).and_hms(0, 0, 0)),
+    ///     Some(NaiveDate::from_ymd(2016, 2, 29).and_hms(0, 0, 0))
+    /// );
+    /// ```
+    pub fn prev_from(&self, from: NaiveDateTime) -> Option<NaiveDateTime> {
+        let limit_year = (from.year() - SEARCH_YEARS).max(MIN_DATE.year());
+        let mut year = from.year();
+        let mut month = from.month();
+        let mut date = from.day();
+        let mut hour = from.hour();
+        let mut minute = from.minute();
+
+        while year >= limit_year {
+            match self.prev_month(month) {
+                Some(m) if m == month => {}
+                Some(m) => {
+                    month = m;
+                    date = 31;
+                    hour = 23;
+                    minute = 59;
+                }
+                None => {
+                    year -= 1;
+                    month = 12;
+                    date = 31;
+                    hour = 23;
+                    minute = 59;
+                    continue;
+                }
+            }
+
+            match self.prev_date_in_month(year, month, date) {
+                Some(d) if d == date => {}
+                Some(d) => {
+                    date = d;
+                    hour = 23;
+                    minute = 59;
+                }
+                None => {
+                    month -= 1;
+                    date = 31;
+                    hour = 23;
+                    minute = 59;
+                    if month < 1 {
+                        year -= 1;
+                        month = 12;
+                    }
+                    continue;
+                }
+            }
+
+            match self.prev_hour(hour) {
+                Some(h) if h == hour => {}
+                Some(h) => {
+                    hour = h;
+                    minute = 59;
+                }
+                None => {
+                    // Date 0 finds no date and borrows from the month
+                    date -= 1;
+                    hour = 23;
+                    minute = 59;
+                    continue;
+                }
+            }
+
+            match self.prev_minute(minute) {
+                Some(m) => {
+                    return NaiveDate::from_ymd_opt(year, month, date)
+                        .and_then(|d| d.and_hms_opt(hour, m, 0));
+                }
+                None if hour == 0 => {
+                    date -= 1;
+                    hour = 23;
+                    minute = 59;
+                }
+                None => {
+                    hour -= 1;
+                    minute = 59;
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns the latest date of the month at or before from matching both date and day
+    fn prev_date_in_month(&self, year: i32, month: u32, from: u32) -> Option<u32> {
+        let mut date = from.min(days_in_month(year, month));
+        while date >= 1 {
+            let d = self.prev_date(date)?;
+            let weekday = NaiveDate::from_ymd(year, month, d)
+                .weekday()
+                .num_days_from_sunday();
+            if self.has_day(weekday) {
+                return Some(d);
+            }
+            date = d - 1;
+        }
+        None
+    }
+
+    /// Returns the earliest date of the month at or after from matching both date and day
+    fn next_date_in_month(&self, year: i32, month: u32, from: u32) -> Option<u32> {
+        let days = days_in_month(year, month);
+        let mut date = from;
+        while let Some(d) = self.next_date(date) {
+            if d > days {
+                return None;
+            }
+            let weekday = NaiveDate::from_ymd(year, month, d)
+                .weekday()
+                .num_days_from_sunday();
+            if self.has_day(weekday) {
+                return Some(d);
+            }
+            date = d + 1;
+        }
+        None
+    }
+
+    /// Returns an Expression running the command
+    ///
+    /// Each field is written canonically, so the raw strings may differ from the original ones.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cron_gate::compact::CompactExpression;
+    ///
+    /// let c = CompactExpression::new("0,30 9-17 * 1,3,5 1-5 command").unwrap();
+    /// let e = c.to_expression("echo 'Yo'");
+    /// assert_eq!(e.to_string(), "0,30 9-17 * 1,3,5 1-5 echo 'Yo'");
+    /// assert_eq!(e.hour_vec, vec![9, 10, 11, 12, 13, 14, 15, 16, 17]);
+    /// ```
+    pub fn to_expression(&self, command: &str) -> Expression {
+        let minute_vec = from_mask(self.minute, 0, 59);
+        let hour_vec = from_mask(u64::from(self.hour), 0, 23);
+        let date_vec = from_mask(u64::from(self.date), 1, 31);
+        let month_vec = from_mask(u64::from(self.month), 1, 12);
+        // '*' on day expands to 0-7 in Expression::new
+        let day_vec: Vec<u32> = if self.day == 0x7f {
+            (0..8).collect()
+        } else {
+            from_mask(u64::from(self.day), 0, 6)
+        };
+        Expression {
+            minute: format_values(&minute_vec, 0, 59),
+            hour: format_values(&hour_vec, 0, 23),
+            date: format_values(&date_vec, 1, 31),
+            month: format_values(&month_vec, 1, 12),
+            day: format_values(&day_vec, 0, 7),
+            command: command.to_string(),
+            minute_vec,
+            hour_vec,
+            date_vec,
+            month_vec,
+            day_vec,
+        }
+    }
+}
+
+impl From<&Expression> for CompactExpression {
+    fn from(e: &Expression) -> CompactExpression {
+        let day = to_mask(&e.day_vec);
+        CompactExpression {
+            minute: to_mask(&e.minute_vec),
+            hour: to_mask(&e.hour_vec) as u32,
+            date: to_mask(&e.date_vec) as u32,
+            month: to_mask(&e.month_vec) as u16,
+            day: ((day | (day >> 7)) & 0x7f) as u8,
+        }
+    }
+}
+
+impl From<CompactExpression> for Expression {
+    fn from(c: CompactExpression) -> Expression {
+        c.to_expression("[command]")
+    }
+}
+
+fn has(mask: u64, value: u32) -> bool {
+    value < 64 && mask & (1 << value) != 0
+}
+
+fn to_mask(v: &[u32]) -> u64 {
+    v.iter()
+        .filter(|i| **i < 64)
+        .fold(0, |mask, i| mask | (1 << i))
+}
+
+fn from_mask(mask: u64, min: u32, max: u32) -> Vec<u32> {
+    (min..(max + 1)).filter(|i| has(mask, *i)).collect()
+}
+
+/// Returns the smallest value in the mask equal to or greater than from
+fn next_in_mask(mask: u64, from: u32) -> Option<u32> {
+    if from >= 64 {
+        return None;
+    }
+    let rest = mask & (!0 << from);
+    if rest == 0 {
+        None
+    } else {
+        Some(rest.trailing_zeros())
+    }
+}
+
+/// Returns the largest value in the mask equal to or less than from
+fn prev_in_mask(mask: u64, from: u32) -> Option<u32> {
+    let rest = if from >= 63 {
+        mask
+    } else {
+        mask & ((1 << (from + 1)) - 1)
+    };
+    if rest == 0 {
+        None
+    } else {
+        Some(63 - rest.leading_zeros())
+    }
+}
+
+/// Returns a field expression of the sorted values: '*', ranges and lists
+pub(crate) fn format_values(values: &[u32], min: u32, max: u32) -> String {
+    if values.len() as u32 == max - min + 1 {
+        return "*".to_string();
+    }
+
+    let mut units: Vec<String> = vec![];
+    let mut i = 0;
+    while i < values.len() {
+        let mut j = i;
+        while j + 1 < values.len() && values[j + 1] == values[j] + 1 {
+            j += 1;
+        }
+        if j - i >= 2 {
+            units.push(format!("{}-{}", values[i], values[j]));
+        } else {
+            for v in &values[i..(j + 1)] {
+                units.push(v.to_string());
+            }
+        }
+        i = j + 1;
+    }
+    units.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_size() {
+        assert_eq!(std::mem::size_of::<CompactExpression>(), 24);
+    }
+
+    #[test]
+    fn test_from_expression_sunday() {
+        let c = CompactExpression::new("* * * * 7 command").unwrap();
+        assert_eq!(c.day, 1);
+        assert!(c.has_day(0));
+        assert!(c.has_day(7));
+        assert!(!c.has_day(1));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for text in &[
+            "* * * * * command",
+            "*/7 */5 * * * command",
+            "0 1-20/3 28 5 2 command",
+            "0 12 1,15 */2 0,6 command",
+        ] {
+            let c = CompactExpression::new(text).unwrap();
+            let e = c.to_expression("command");
+            assert_eq!(CompactExpression::from(&e), c, "{}", text);
+            assert_eq!(Expression::new(&e.to_string()).unwrap(), e, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_next_in_mask() {
+        let mask = to_mask(&[3, 10, 59]);
+        assert_eq!(next_in_mask(mask, 0), Some(3));
+        assert_eq!(next_in_mask(mask, 3), Some(3));
+        assert_eq!(next_in_mask(mask, 4), Some(10));
+        assert_eq!(next_in_mask(mask, 59), Some(59));
+        assert_eq!(next_in_mask(mask, 60), None);
+        assert_eq!(next_in_mask(mask, 64), None);
+    }
+
+    #[test]
+    fn test_prev_in_mask() {
+        let mask = to_mask(&[3, 10, 59]);
+        assert_eq!(prev_in_mask(mask, 2), None);
+        assert_eq!(prev_in_mask(mask, 3), Some(3));
+        assert_eq!(prev_in_mask(mask, 9), Some(3));
+        assert_eq!(prev_in_mask(mask, 60), Some(59));
+        assert_eq!(prev_in_mask(mask, 64), Some(59));
+    }
+
+    #[test]
+    fn test_from_out_of_range() {
+        let c = CompactExpression::new("0 0 * * * command").unwrap();
+        let last = MAX_DATE.and_hms(0, 0, 0);
+        assert_eq!(c.next_from(last), Some(last));
+        assert_eq!(c.next_from(MAX_DATE.and_hms(0, 1, 0)), None);
+        let c = CompactExpression::new("30 * * * * command").unwrap();
+        assert_eq!(c.prev_from(MIN_DATE.and_hms(0, 0, 0)), None);
+    }
+
+    #[test]
+    fn test_prev_from_same_as_scan() {
+        let from = NaiveDate::from_ymd(2019, 3, 1).and_hms(0, 30, 0);
+        for text in &[
+            "* * * * * command",
+            "*/7 */5 * * * command",
+            "0 1-20/3 28 5 * command",
+            "0 12 1,15 */2 0,6 command",
+            "59 23 31 * * command",
+            "30 9 * * 1-5 command",
+        ] {
+            let c = CompactExpression::new(text).unwrap();
+            let mut scan = from;
+            while !c.matches(scan) {
+                scan -= chrono::Duration::minutes(1);
+            }
+            assert_eq!(c.prev_from(from), Some(scan), "{}", text);
+            assert!(c.next_from(scan).unwrap() == scan);
+        }
+        let c = CompactExpression::new("0 0 30 2 * command").unwrap();
+        assert_eq!(c.prev_from(from), None);
+    }
+
+    #[test]
+    fn test_format_values() {
+        assert_eq!(format_values(&[0, 1, 2, 3], 0, 3), "*");
+        assert_eq!(format_values(&[1, 2, 3, 5, 7, 8], 0, 10), "1-3,5,7,8");
+        assert_eq!(format_values(&[4], 0, 10), "4");
+    }
+}
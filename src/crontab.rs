@@ -0,0 +1,183 @@
+use crate::dialect::{expand_macro, replace_names, MONTH_NAMES, WEEKDAY_NAMES};
+use crate::expression::Expression;
+
+/// An expression on a line of a crontab
+#[derive(Debug, PartialEq, Clone)]
+pub struct Entry {
+    /// 1-based line number
+    pub line: usize,
+    pub expression: Expression,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Crontab {
+    pub entries: Vec<Entry>,
+    /// Environment variables like `SHELL=/bin/sh` in order of appearance
+    pub variables: Vec<(String, String)>,
+    /// Lines which cannot be parsed: 1-based line number and message
+    pub errors: Vec<(usize, String)>,
+    /// Lines valid for cron which have no datetime, like `@reboot`: 1-based line number and message
+    pub unsupported: Vec<(usize, String)>,
+}
+
+impl Crontab {
+    /// Returns a Crontab parsed from the text of a user crontab
+    ///
+    /// Blank lines and comments are skipped.
+    /// Macros like `@daily` and names like `JAN` and `mon` are accepted as cron does.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cron_gate::crontab::Crontab;
+    ///
+    /// let c = Crontab::parse("# backup\nSHELL=/bin/bash\n0 3 * * * backup.sh\n0 25 * * * oops\n@reboot up.sh\n");
+    /// assert_eq!(c.variables, vec![("SHELL".to_string(), "/bin/bash".to_string())]);
+    /// assert_eq!(c.entries.len(), 1);
+    /// assert_eq!(c.entries[0].line, 3);
+    /// assert_eq!(c.entries[0].expression.command, "backup.sh");
+    /// assert_eq!(c.errors[0].0, 4);
+    /// assert_eq!(c.unsupported[0].0, 5);
+    /// ```
+    pub fn parse(text: &str) -> Crontab {
+        let mut crontab = Crontab {
+            entries: vec![],
+            variables: vec![],
+            errors: vec![],
+            unsupported: vec![],
+        };
+
+        for (index, raw) in text.lines().enumerate() {
+            let line = index + 1;
+            let trimmed = raw.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            if let Some(variable) = parse_variable(trimmed) {
+                crontab.variables.push(variable);
+                continue;
+            }
+
+            if trimmed.starts_with("@reboot") {
+                crontab.unsupported.push((
+                    line,
+                    "'@reboot' runs at startup, not at a datetime".to_string(),
+                ));
+                continue;
+            }
+
+            match Expression::new(&normalize(trimmed)) {
+                Ok(expression) => crontab.entries.push(Entry { line, expression }),
+                Err(e) => crontab.errors.push((line, e)),
+            }
+        }
+        crontab
+    }
+}
+
+/// Returns the line with a macro like `@daily` expanded into fields
+/// and names of months and weekdays replaced with numbers
+///
+/// Invalid names are left for `Expression::new` to report.
+pub(crate) fn normalize(line: &str) -> String {
+    let mut fields: Vec<String> = line.split_whitespace().map(|f| f.to_string()).collect();
+    if let Some(expanded) = fields.first().and_then(|f| expand_macro(f)) {
+        fields.splice(..1, expanded.split(' ').map(|f| f.to_string()));
+    } else if fields.len() >= 5 {
+        for (index, names, offset) in [(3, &MONTH_NAMES[..], 1), (4, &WEEKDAY_NAMES[..], 0)] {
+            if let Ok(replaced) = replace_names(&fields[index], names, offset) {
+                fields[index] = replaced;
+            }
+        }
+    }
+    fields.join(" ")
+}
+
+/// Parses `NAME = value`, where the value may be quoted
+fn parse_variable(line: &str) -> Option<(String, String)> {
+    let index = line.find('=')?;
+    let name = line[..index].trim();
+    let mut chars = name.chars();
+    let head = chars.next()?;
+    if !(head.is_ascii_alphabetic() || head == '_')
+        || !chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return None;
+    }
+
+    let value = line[(index + 1)..].trim();
+    let unquoted = if value.len() >= 2
+        && ((value.starts_with('"') && value.ends_with('"'))
+            || (value.starts_with('\'') && value.ends_with('\'')))
+    {
+        &value[1..(value.len() - 1)]
+    } else {
+        value
+    };
+    Some((name.to_string(), unquoted.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let c = Crontab::parse(
+            "\n  # comment\nMAILTO=\"ops@example.com\"\n*/5 * * * * a\n  30 9 * * 1-5 b c  \n",
+        );
+        assert_eq!(
+            c.variables,
+            vec![("MAILTO".to_string(), "ops@example.com".to_string())]
+        );
+        assert_eq!(c.entries.len(), 2);
+        assert_eq!(c.entries[0].line, 4);
+        assert_eq!(c.entries[1].line, 5);
+        assert_eq!(c.entries[1].expression.command, "b c");
+        assert!(c.errors.is_empty());
+    }
+
+    #[test]
+    fn test_parse_macros_and_names() {
+        let c = Crontab::parse(
+            "@daily a
+0 9 * jan-mar Mon-FRI b
+@reboot c
+@often d
+",
+        );
+        assert_eq!(c.entries.len(), 2);
+        assert_eq!(c.entries[0].expression.schedule(), "0 0 * * *");
+        assert_eq!(c.entries[0].expression.command, "a");
+        assert_eq!(c.entries[1].expression.month_vec, vec![1, 2, 3]);
+        assert_eq!(c.entries[1].expression.day_vec, vec![1, 2, 3, 4, 5]);
+        assert_eq!(c.unsupported.len(), 1);
+        assert_eq!(c.unsupported[0].0, 3);
+        assert_eq!(c.errors.len(), 1);
+        assert_eq!(c.errors[0].0, 4);
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("@hourly  a  b"), "0 * * * * a b");
+        assert_eq!(normalize("0 9 * DEC sun a"), "0 9 * 12 0 a");
+        assert_eq!(normalize("0 9 * JAN-X * a"), "0 9 * JAN-X * a");
+        assert_eq!(normalize("nope"), "nope");
+    }
+
+    #[test]
+    fn test_parse_variable() {
+        assert_eq!(
+            parse_variable("PATH = /usr/bin:/bin"),
+            Some(("PATH".to_string(), "/usr/bin:/bin".to_string()))
+        );
+        assert_eq!(
+            parse_variable("EMPTY=''"),
+            Some(("EMPTY".to_string(), "".to_string()))
+        );
+        assert_eq!(parse_variable("* * * * * A=1 command"), None);
+        assert_eq!(parse_variable("=value"), None);
+        assert_eq!(parse_variable("1A=value"), None);
+    }
+}
@@ -0,0 +1,291 @@
+use crate::clock::{Clock, FixedClock};
+use crate::expression::DATE_FORMAT;
+use chrono::format::{Item, StrftimeItems};
+use chrono::offset::TimeZone;
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime};
+
+/// Formats of local datetimes accepted by `parse_datetime`
+const DATETIME_FORMATS: [&str; 8] = [
+    DATE_FORMAT,
+    "%Y/%m/%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y%m%dT%H%M",
+    "%Y%m%dT%H%M%S",
+];
+
+/// Formats of datetimes with an offset accepted by `parse_datetime` besides RFC 3339
+const OFFSET_FORMATS: [&str; 2] = ["%Y-%m-%dT%H:%M%z", "%Y-%m-%d %H:%M%z"];
+
+const DATE_FORMATS: [&str; 2] = ["%Y/%m/%d", "%Y-%m-%d"];
+
+/// Returns a datetime parsed from the text
+///
+/// The text can be
+/// - a local datetime: `2019/05/28 09:00`, `2019-05-28 09:00`, `2019-05-28T09:00:00`
+/// - ISO 8601 / RFC 3339 with an offset: `2019-05-28T09:00:00+09:00`, `2019-05-28T00:00Z`
+/// - a date: `2019/05/28`, `2019-05-28` (at midnight)
+/// - Unix epoch seconds: `1559001600`, `@1559001600`
+/// - relative to now: `now`, `+2h`, `-30m`, `+1d`, `+1w`, `today 09:00`, `tomorrow 09:00`, `yesterday`
+///
+/// # Examples
+///
+/// ```
+/// use chrono::offset::TimeZone;
+/// use chrono::Local;
+/// use cron_gate::datetime::parse_datetime;
+///
+/// let now = Local.datetime_from_str("2019/05/28 10:15", "%Y/%m/%d %H:%M").unwrap();
+/// let parse = |text| parse_datetime(text, now).unwrap().format("%Y/%m/%d %H:%M").to_string();
+///
+/// assert_eq!(parse("2019-06-01"), "2019/06/01 00:00");
+/// assert_eq!(parse("2019-06-01T09:30"), "2019/06/01 09:30");
+/// assert_eq!(parse("+2h"), "2019/05/28 12:15");
+/// assert_eq!(parse("tomorrow 09:00"), "2019/05/29 09:00");
+/// assert!(parse_datetime("next week", now).is_err());
+/// ```
+pub fn parse_datetime(text: &str, now: DateTime<Local>) -> Result<DateTime<Local>, String> {
+    parse_datetime_with_clock(text, &FixedClock(now))
+}
+
+/// Returns a datetime parsed from the text as `parse_datetime` does,
+/// reading the clock only if the text is relative to now
+///
+/// # Examples
+///
+/// ```
+/// use chrono::offset::TimeZone;
+/// use chrono::Local;
+/// use cron_gate::clock::SystemClock;
+/// use cron_gate::datetime::parse_datetime_with_clock;
+///
+/// assert_eq!(
+///     parse_datetime_with_clock("2019-06-01", &SystemClock).unwrap(),
+///     Local.ymd(2019, 6, 1).and_hms(0, 0, 0)
+/// );
+/// ```
+pub fn parse_datetime_with_clock<C: Clock + ?Sized>(
+    text: &str,
+    clock: &C,
+) -> Result<DateTime<Local>, String> {
+    let text = text.trim();
+    let lower = text.to_lowercase();
+
+    if lower == "now" {
+        return Ok(clock.now());
+    }
+    if lower.starts_with('+') || lower.starts_with('-') {
+        let duration = parse_relative(&lower)?;
+        return clock
+            .now()
+            .checked_add_signed(duration)
+            .ok_or_else(|| format!("'{}' is out of range", text));
+    }
+    for (keyword, days) in [("yesterday", -1), ("today", 0), ("tomorrow", 1)].iter() {
+        if let Some(rest) = lower.strip_prefix(keyword) {
+            let time = parse_time(rest.trim())?;
+            let date = clock.now().date().naive_local() + Duration::days(*days);
+            return from_local(&date.and_time(time), text);
+        }
+    }
+
+    let epoch = lower.strip_prefix('@').unwrap_or(&lower);
+    if !epoch.is_empty() && epoch.bytes().all(|b| b.is_ascii_digit()) {
+        let seconds = epoch
+            .parse::<i64>()
+            .map_err(|e| format!("Invalid epoch seconds '{}': {}", text, e))?;
+        return Local
+            .timestamp_opt(seconds, 0)
+            .single()
+            .ok_or_else(|| format!("Epoch seconds '{}' are out of range", text));
+    }
+
+    // "Z" is not accepted by "%z"
+    let with_offset = if text.ends_with('Z') || text.ends_with('z') {
+        format!("{}+00:00", &text[..(text.len() - 1)])
+    } else {
+        text.to_string()
+    };
+    if let Ok(d) = DateTime::parse_from_rfc3339(&with_offset) {
+        return Ok(d.with_timezone(&Local));
+    }
+    for format in OFFSET_FORMATS.iter() {
+        if let Ok(d) = DateTime::parse_from_str(&with_offset, format) {
+            return Ok(d.with_timezone(&Local));
+        }
+    }
+    for format in DATETIME_FORMATS.iter() {
+        if let Ok(d) = NaiveDateTime::parse_from_str(text, format) {
+            return from_local(&d, text);
+        }
+    }
+    for format in DATE_FORMATS.iter() {
+        if let Ok(d) = NaiveDate::parse_from_str(text, format) {
+            return from_local(&d.and_hms(0, 0, 0), text);
+        }
+    }
+
+    Err(format!(
+        "Cannot parse '{}': should be like '2019/05/28 09:00', '2019-05-28T09:00:00+09:00', '2019-05-28', '1559001600', 'now', '+2h' or 'tomorrow 09:00'",
+        text
+    ))
+}
+
+/// Returns an error if the strftime format has an invalid specifier
+///
+/// # Examples
+///
+/// ```
+/// use cron_gate::datetime::validate_format;
+///
+/// assert!(validate_format("%Y-%m-%d %H:%M").is_ok());
+/// assert!(validate_format("%Y-%m-%d %Q").is_err());
+/// ```
+pub fn validate_format(format: &str) -> Result<(), String> {
+    if StrftimeItems::new(format).any(|item| item == Item::Error) {
+        Err(format!("Invalid datetime format '{}'", format))
+    } else {
+        Ok(())
+    }
+}
+
+/// Parses '+2h', '-30m', '+1d', '+1w' and '+90s'
+fn parse_relative(text: &str) -> Result<Duration, String> {
+    let sign = if text.starts_with('-') { -1 } else { 1 };
+    let body = text[1..].trim();
+    let index = body
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(body.len());
+    let amount = body[..index]
+        .parse::<i64>()
+        .map_err(|e| format!("Invalid relative datetime '{}': {}", text, e))?
+        * sign;
+    let unit = match body[index..].trim() {
+        "s" | "sec" | "second" | "seconds" => 1,
+        "m" | "min" | "minute" | "minutes" => 60,
+        "h" | "hour" | "hours" => 60 * 60,
+        "d" | "day" | "days" => 24 * 60 * 60,
+        "w" | "week" | "weeks" => 7 * 24 * 60 * 60,
+        unit => {
+            return Err(format!(
+                "Invalid unit '{}' of '{}': should be s, m, h, d or w",
+                unit, text
+            ))
+        }
+    };
+    // `Duration::seconds` panics out of its range, which milliseconds never exceed
+    amount
+        .checked_mul(unit)
+        .and_then(|seconds| seconds.checked_mul(1000))
+        .map(Duration::milliseconds)
+        .ok_or_else(|| format!("'{}' is out of range", text))
+}
+
+/// Parses 'H:M', 'H:M:S' or '' as midnight
+fn parse_time(text: &str) -> Result<NaiveTime, String> {
+    if text.is_empty() {
+        return Ok(NaiveTime::from_hms(0, 0, 0));
+    }
+    NaiveTime::parse_from_str(text, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(text, "%H:%M:%S"))
+        .map_err(|e| format!("Invalid time '{}': {}", text, e))
+}
+
+fn from_local(datetime: &NaiveDateTime, text: &str) -> Result<DateTime<Local>, String> {
+    Local
+        .from_local_datetime(datetime)
+        .earliest()
+        .ok_or_else(|| format!("'{}' does not exist in the local time zone", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::UnreadClock;
+    use chrono::{FixedOffset, Utc};
+
+    fn now() -> DateTime<Local> {
+        Local
+            .datetime_from_str("2019/05/28 10:15", DATE_FORMAT)
+            .unwrap()
+    }
+
+    fn parse(text: &str) -> DateTime<Local> {
+        parse_datetime(text, now()).unwrap()
+    }
+
+    fn local(text: &str) -> DateTime<Local> {
+        Local.datetime_from_str(text, DATE_FORMAT).unwrap()
+    }
+
+    #[test]
+    fn test_parse_datetime_local() {
+        assert_eq!(parse("2019/5/28 9:0"), local("2019/05/28 09:00"));
+        assert_eq!(parse("2019-05-28 09:00:00"), local("2019/05/28 09:00"));
+        assert_eq!(parse("2019-05-28T09:00"), local("2019/05/28 09:00"));
+        assert_eq!(parse("20190528T0900"), local("2019/05/28 09:00"));
+        assert_eq!(parse("2019/05/28"), local("2019/05/28 00:00"));
+    }
+
+    #[test]
+    fn test_parse_datetime_offset() {
+        let expected = Utc.ymd(2019, 5, 28).and_hms(0, 0, 0);
+        assert_eq!(parse("2019-05-28T09:00:00+09:00"), expected);
+        assert_eq!(parse("2019-05-28T00:00:00Z"), expected);
+        assert_eq!(parse("2019-05-28T00:00Z"), expected);
+        assert_eq!(parse("2019-05-28T09:00+0900"), expected);
+        assert_eq!(
+            parse("2019-05-28T09:00:00+09:00").with_timezone(&FixedOffset::east(9 * 3600)),
+            FixedOffset::east(9 * 3600)
+                .ymd(2019, 5, 28)
+                .and_hms(9, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_parse_datetime_epoch() {
+        let expected = Utc.ymd(2019, 5, 28).and_hms(0, 0, 0);
+        assert_eq!(parse("1559001600"), expected);
+        assert_eq!(parse("@1559001600"), expected);
+    }
+
+    #[test]
+    fn test_parse_datetime_relative() {
+        assert_eq!(parse("now"), now());
+        assert_eq!(parse("NOW"), now());
+        assert_eq!(parse("+2h"), local("2019/05/28 12:15"));
+        assert_eq!(parse("-30m"), local("2019/05/28 09:45"));
+        assert_eq!(parse("+1d"), local("2019/05/29 10:15"));
+        assert_eq!(parse("+ 2 weeks"), local("2019/06/11 10:15"));
+        assert_eq!(parse("today 09:00"), local("2019/05/28 09:00"));
+        assert_eq!(parse("Tomorrow 09:00"), local("2019/05/29 09:00"));
+        assert_eq!(parse("yesterday"), local("2019/05/27 00:00"));
+    }
+
+    #[test]
+    fn test_parse_datetime_with_clock() {
+        let parse = |text| parse_datetime_with_clock(text, &UnreadClock).unwrap();
+        assert_eq!(parse("2019/05/28 09:00"), local("2019/05/28 09:00"));
+        assert_eq!(parse("2019-05-28"), local("2019/05/28 00:00"));
+        assert_eq!(parse("@1559001600"), Local.timestamp(1559001600, 0));
+        assert_eq!(
+            parse("2019-05-28T00:00Z"),
+            Utc.ymd(2019, 5, 28).and_hms(0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_parse_datetime_error() {
+        assert!(parse_datetime("", now()).is_err());
+        assert!(parse_datetime("+2y", now()).is_err());
+        assert!(parse_datetime("+h", now()).is_err());
+        assert!(parse_datetime("tomorrow 25:00", now()).is_err());
+        assert!(parse_datetime("2019/13/01", now()).is_err());
+        assert!(parse_datetime("99999999999999999", now()).is_err());
+        assert!(parse_datetime("+99999999999999w", now()).is_err());
+        assert!(parse_datetime("+9999999999d", now()).is_err());
+        assert!(parse_datetime("-9999999999d", now()).is_err());
+    }
+}
@@ -0,0 +1,208 @@
+//! English description of an expression
+
+use crate::expression::{Expression, MONTH_NAMES};
+
+const WEEKDAY_NAMES: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+/// Times are listed up to this number instead of describing the fields
+const MAX_TIMES: usize = 4;
+
+/// Returns a description of when the expression fires
+///
+/// Date and day are both required to match as `Expression` does.
+///
+/// # Examples
+///
+/// ```
+/// use cron_gate::describe::describe;
+/// use cron_gate::expression::Expression;
+///
+/// let describe_str = |text| describe(&Expression::new(text).unwrap());
+/// assert_eq!(describe_str("0 9 * * 1-5"), "At 09:00, on Monday through Friday");
+/// assert_eq!(describe_str("*/15 * * * *"), "Every 15 minutes");
+/// assert_eq!(
+///     describe_str("0,30 9-17 1,15 * *"),
+///     "At minutes 0 and 30 of hours 9 through 17, on days 1 and 15 of the month"
+/// );
+/// ```
+pub fn describe(expression: &Expression) -> String {
+    let mut parts = vec![time(&expression.minute_vec, &expression.hour_vec)];
+
+    let date_all = expression.date_vec.len() == 31;
+    if !date_all {
+        parts.push(format!(
+            "on {} of the month",
+            plural("day", &expression.date_vec, |d| d.to_string())
+        ));
+    }
+    if expression.month_vec.len() < 12 {
+        parts.push(format!(
+            "in {}",
+            words(&expression.month_vec, |m| MONTH_NAMES[(m - 1) as usize]
+                .to_string())
+        ));
+    }
+    let mut days: Vec<u32> = expression.day_vec.iter().map(|d| d % 7).collect();
+    days.sort_unstable();
+    days.dedup();
+    if days.len() < 7 {
+        let names = words(&days, |d| WEEKDAY_NAMES[d as usize].to_string());
+        if date_all {
+            parts.push(format!("on {}", names));
+        } else {
+            parts.push(format!("only on {}", names));
+        }
+    }
+    parts.join(", ")
+}
+
+fn time(minutes: &[u32], hours: &[u32]) -> String {
+    if minutes.len() * hours.len() <= MAX_TIMES {
+        let times: Vec<String> = hours
+            .iter()
+            .flat_map(|h| minutes.iter().map(move |m| format!("{:02}:{:02}", h, m)))
+            .collect();
+        return format!("At {}", join(&times));
+    }
+
+    let minute = if minutes.len() == 60 {
+        "Every minute".to_string()
+    } else {
+        match progression(minutes, 60) {
+            Some((step, true)) => format!("Every {} minutes", step),
+            Some((step, false)) => format!(
+                "Every {} minutes from {} through {}",
+                step,
+                minutes[0],
+                minutes[minutes.len() - 1]
+            ),
+            None => format!("At {}", plural("minute", minutes, |m| m.to_string())),
+        }
+    };
+    if hours.len() == 24 {
+        return minute;
+    }
+    let hour = match progression(hours, 24) {
+        Some((step, true)) => format!("every {} hours", step),
+        Some((step, false)) => format!(
+            "every {} hours from {} through {}",
+            step,
+            hours[0],
+            hours[hours.len() - 1]
+        ),
+        None => plural("hour", hours, |h| h.to_string()),
+    };
+    format!("{} of {}", minute, hour)
+}
+
+/// Returns the step of the values in a progression of 3 or more values,
+/// and true if it starts at 0 and reaches the end of the cycle
+fn progression(values: &[u32], cycle: u32) -> Option<(u32, bool)> {
+    if values.len() < 3 {
+        return None;
+    }
+    let step = values[1] - values[0];
+    if step < 2 || values.windows(2).any(|w| w[1] - w[0] != step) {
+        return None;
+    }
+    Some((
+        step,
+        values[0] == 0 && values[values.len() - 1] + step >= cycle,
+    ))
+}
+
+/// Returns like "days 1 and 15" or "day 1"
+fn plural<F: Fn(u32) -> String>(unit: &str, values: &[u32], name: F) -> String {
+    let s = if values.len() == 1 { "" } else { "s" };
+    format!("{}{} {}", unit, s, words(values, name))
+}
+
+/// Returns the sorted values in words having runs of 3 or more as "a through b"
+fn words<F: Fn(u32) -> String>(values: &[u32], name: F) -> String {
+    let mut units: Vec<String> = vec![];
+    let mut i = 0;
+    while i < values.len() {
+        let mut j = i;
+        while j + 1 < values.len() && values[j + 1] == values[j] + 1 {
+            j += 1;
+        }
+        if j - i >= 2 {
+            units.push(format!("{} through {}", name(values[i]), name(values[j])));
+        } else {
+            units.extend(values[i..=j].iter().map(|v| name(*v)));
+        }
+        i = j + 1;
+    }
+    join(&units)
+}
+
+fn join(units: &[String]) -> String {
+    match units.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} and {}", rest.join(", "), last),
+        Some((last, _)) => last.to_string(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn describe_str(text: &str) -> String {
+        describe(&Expression::new(text).unwrap())
+    }
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(describe_str("* * * * *"), "Every minute");
+        assert_eq!(describe_str("* 9 * * *"), "Every minute of hour 9");
+        assert_eq!(
+            describe_str("5-59/10 * * * *"),
+            "Every 10 minutes from 5 through 55"
+        );
+        assert_eq!(describe_str("0 */2 * * *"), "At minute 0 of every 2 hours");
+        assert_eq!(describe_str("0 0,12 * * *"), "At 00:00 and 12:00");
+        assert_eq!(
+            describe_str("0 0 1 1,4,7,10 *"),
+            "At 00:00, on day 1 of the month, in January, April, July and October"
+        );
+        assert_eq!(
+            describe_str("0 0 1-7 * 0"),
+            "At 00:00, on days 1 through 7 of the month, only on Sunday"
+        );
+        assert_eq!(
+            describe_str("0 0 * * 0,6,7"),
+            "At 00:00, on Sunday and Saturday"
+        );
+    }
+
+    #[test]
+    fn test_progression() {
+        assert_eq!(progression(&[0, 15, 30, 45], 60), Some((15, true)));
+        assert_eq!(progression(&[5, 15, 25], 60), Some((10, false)));
+        assert_eq!(
+            progression(&[0, 7, 14, 21, 28, 35, 42, 49, 56], 60),
+            Some((7, true))
+        );
+        assert_eq!(progression(&[0, 30], 60), None);
+        assert_eq!(progression(&[1, 2, 3], 60), None);
+        assert_eq!(progression(&[0, 10, 30], 60), None);
+    }
+
+    #[test]
+    fn test_words() {
+        let name = |v: u32| v.to_string();
+        assert_eq!(words(&[1], name), "1");
+        assert_eq!(words(&[1, 2], name), "1 and 2");
+        assert_eq!(words(&[1, 2, 3, 5], name), "1 through 3 and 5");
+        assert_eq!(words(&[1, 3, 5], name), "1, 3 and 5");
+    }
+}
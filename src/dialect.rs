@@ -0,0 +1,851 @@
+//! Dialects of cron used by other schedulers
+//!
+//! A spec in a dialect is normalized into an `Expression`,
+//! keeping what the dialect does differently in `Schedule`.
+
+use crate::compact::format_values;
+use crate::expression::{parse_block, Expression};
+use crate::jenkins;
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone};
+use std::str::FromStr;
+
+/// Names of the dialects accepted by `Dialect::from_str`
+pub const DIALECTS: [&str; 4] = ["kubernetes", "github-actions", "eventbridge", "jenkins"];
+
+pub(crate) const MONTH_NAMES: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+pub(crate) const WEEKDAY_NAMES: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Dialect {
+    /// `spec.schedule` of a Kubernetes CronJob: macros like `@daily`, names and `?`
+    Kubernetes,
+    /// `on.schedule[].cron` of a GitHub Actions workflow: five fields with names in UTC
+    GithubActions,
+    /// `cron()` and `rate()` of an AWS EventBridge (CloudWatch Events) rule:
+    /// six fields with a year, `?` and weekdays from 1 for Sunday in UTC
+    EventBridge,
+    /// Jenkins `H` hashed by a seed like `H H(0-7) * * *`, see `jenkins::resolve`
+    Jenkins,
+}
+
+impl FromStr for Dialect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Dialect, String> {
+        match s {
+            "kubernetes" => Ok(Dialect::Kubernetes),
+            "github-actions" => Ok(Dialect::GithubActions),
+            "eventbridge" => Ok(Dialect::EventBridge),
+            "jenkins" => Ok(Dialect::Jenkins),
+            _ => Err(format!(
+                "Unknown dialect '{}': should be one of {}",
+                s,
+                DIALECTS.join(", ")
+            )),
+        }
+    }
+}
+
+/// A spec parsed in a dialect
+#[derive(Debug, PartialEq, Clone)]
+pub struct Schedule {
+    pub expression: Expression,
+    /// Fires when either the date or the weekday matches, as Vixie cron does
+    /// when both are restricted, instead of when both match
+    pub day_or: bool,
+    /// Years it fires in, or every year if None
+    pub years: Option<Vec<i32>>,
+    /// Notes on how the scheduler treats the spec
+    pub warnings: Vec<String>,
+}
+
+impl Schedule {
+    /// Returns earler datetimes from in the time zone of `from`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::{TimeZone, Utc};
+    /// use cron_gate::dialect::Dialect;
+    ///
+    /// // Every Friday and every 13th
+    /// let s = Dialect::Kubernetes.parse("0 0 13 * FRI").unwrap();
+    /// let from = Utc.ymd(2019, 10, 1).and_hms(0, 0, 0);
+    /// assert_eq!(s.earler_excuting_datetimes(from, 3), [
+    ///     Utc.ymd(2019, 10, 4).and_hms(0, 0, 0),
+    ///     Utc.ymd(2019, 10, 11).and_hms(0, 0, 0),
+    ///     Utc.ymd(2019, 10, 13).and_hms(0, 0, 0),
+    /// ]);
+    /// ```
+    pub fn earler_excuting_datetimes<Tz: TimeZone>(
+        &self,
+        from: DateTime<Tz>,
+        count: usize,
+    ) -> Vec<DateTime<Tz>> {
+        let years = match &self.years {
+            Some(years) => years,
+            None => return self.in_any_year(from, count),
+        };
+
+        let mut ret = vec![];
+        for year in years.iter().filter(|y| **y >= from.year()) {
+            if ret.len() >= count {
+                break;
+            }
+            let start = if *year == from.year() {
+                from.clone()
+            } else {
+                match from
+                    .timezone()
+                    .from_local_datetime(&NaiveDate::from_ymd(*year, 1, 1).and_hms(0, 0, 0))
+                    .earliest()
+                {
+                    Some(start) => start,
+                    None => continue,
+                }
+            };
+            let rest = count - ret.len();
+            ret.extend(
+                self.in_any_year(start, rest)
+                    .into_iter()
+                    .take_while(|d| d.year() == *year),
+            );
+        }
+        ret
+    }
+
+    /// Returns datetimes before `before` in the time zone of it, the latest first
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::{TimeZone, Utc};
+    /// use cron_gate::dialect::Dialect;
+    ///
+    /// let s = Dialect::EventBridge.parse("cron(0 0 13 * ? 2018,2019)").unwrap();
+    /// let before = Utc.ymd(2019, 2, 1).and_hms(0, 0, 0);
+    /// assert_eq!(s.previous_datetimes(before, 3), [
+    ///     Utc.ymd(2019, 1, 13).and_hms(0, 0, 0),
+    ///     Utc.ymd(2018, 12, 13).and_hms(0, 0, 0),
+    ///     Utc.ymd(2018, 11, 13).and_hms(0, 0, 0),
+    /// ]);
+    /// ```
+    pub fn previous_datetimes<Tz: TimeZone>(
+        &self,
+        before: DateTime<Tz>,
+        count: usize,
+    ) -> Vec<DateTime<Tz>> {
+        let years = match &self.years {
+            Some(years) => years,
+            None => return self.previous_in_any_year(before, count),
+        };
+
+        let mut ret = vec![];
+        for year in years.iter().rev().filter(|y| **y <= before.year()) {
+            if ret.len() >= count {
+                break;
+            }
+            let end = if *year == before.year() {
+                before.clone()
+            } else {
+                match NaiveDate::from_ymd_opt(*year + 1, 1, 1).and_then(|d| {
+                    before
+                        .timezone()
+                        .from_local_datetime(&d.and_hms(0, 0, 0))
+                        .earliest()
+                }) {
+                    Some(end) => end,
+                    None => continue,
+                }
+            };
+            let rest = count - ret.len();
+            ret.extend(
+                self.previous_in_any_year(end, rest)
+                    .into_iter()
+                    .take_while(|d| d.year() == *year),
+            );
+        }
+        ret
+    }
+
+    /// Returns at most `count` datetimes from `from` until `to` (exclusive)
+    /// in the time zone of `from`
+    pub fn datetimes_between<Tz: TimeZone>(
+        &self,
+        from: DateTime<Tz>,
+        to: DateTime<Tz>,
+        count: usize,
+    ) -> Vec<DateTime<Tz>> {
+        let years = match &self.years {
+            Some(years) => years,
+            None => return self.between_in_any_year(from, to, count),
+        };
+
+        let mut ret = vec![];
+        for year in years
+            .iter()
+            .filter(|y| **y >= from.year() && **y <= to.year())
+        {
+            if ret.len() >= count {
+                break;
+            }
+            let start = if *year == from.year() {
+                from.clone()
+            } else {
+                match start_of_year(&from.timezone(), *year) {
+                    Some(start) => start,
+                    None => continue,
+                }
+            };
+            let end = match start_of_year(&from.timezone(), *year + 1) {
+                Some(end) if end < to => end,
+                _ => to.clone(),
+            };
+            let rest = count - ret.len();
+            ret.extend(self.between_in_any_year(start, end, rest));
+        }
+        ret
+    }
+
+    fn in_any_year<Tz: TimeZone>(&self, from: DateTime<Tz>, count: usize) -> Vec<DateTime<Tz>> {
+        let mut ret: Vec<DateTime<Tz>> = self
+            .expressions()
+            .iter()
+            .flat_map(|e| e.earler_excuting_datetimes(from.clone(), count))
+            .collect();
+        ret.sort();
+        ret.dedup();
+        ret.truncate(count);
+        ret
+    }
+
+    fn between_in_any_year<Tz: TimeZone>(
+        &self,
+        from: DateTime<Tz>,
+        to: DateTime<Tz>,
+        count: usize,
+    ) -> Vec<DateTime<Tz>> {
+        let mut ret: Vec<DateTime<Tz>> = self
+            .expressions()
+            .iter()
+            .flat_map(|e| e.datetimes_between(from.clone(), to.clone(), count))
+            .collect();
+        ret.sort();
+        ret.dedup();
+        ret.truncate(count);
+        ret
+    }
+
+    fn previous_in_any_year<Tz: TimeZone>(
+        &self,
+        before: DateTime<Tz>,
+        count: usize,
+    ) -> Vec<DateTime<Tz>> {
+        let mut ret: Vec<DateTime<Tz>> = self
+            .expressions()
+            .iter()
+            .flat_map(|e| e.previous_datetimes(before.clone(), count))
+            .collect();
+        ret.sort_by(|a, b| b.cmp(a));
+        ret.dedup();
+        ret.truncate(count);
+        ret
+    }
+
+    /// Returns the expression, or the expressions by the date and by the weekday if `day_or`
+    fn expressions(&self) -> Vec<Expression> {
+        if !self.day_or {
+            return vec![self.expression.clone()];
+        }
+
+        let mut by_date = self.expression.clone();
+        by_date.day = "*".to_string();
+        by_date.day_vec = (0..8).collect();
+        let mut by_day = self.expression.clone();
+        by_day.date = "*".to_string();
+        by_day.date_vec = (1..32).collect();
+        vec![by_date, by_day]
+    }
+}
+
+/// Returns the first moment of the year in the time zone
+fn start_of_year<Tz: TimeZone>(tz: &Tz, year: i32) -> Option<DateTime<Tz>> {
+    NaiveDate::from_ymd_opt(year, 1, 1)
+        .and_then(|d| tz.from_local_datetime(&d.and_hms(0, 0, 0)).earliest())
+}
+
+impl Dialect {
+    /// Returns the schedule of the spec in the dialect
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cron_gate::dialect::Dialect;
+    ///
+    /// let s = Dialect::Kubernetes.parse("@weekly").unwrap();
+    /// assert_eq!(s.expression.schedule(), "0 0 * * 0");
+    ///
+    /// let s = Dialect::GithubActions.parse("30 5 * JAN-MAR MON-FRI").unwrap();
+    /// assert_eq!(s.expression.month_vec, vec![1, 2, 3]);
+    /// assert_eq!(s.expression.day_vec, vec![1, 2, 3, 4, 5]);
+    ///
+    /// assert!(Dialect::GithubActions.parse("@daily").is_err());
+    /// assert!(Dialect::Kubernetes.parse("CRON_TZ=UTC 0 0 * * *").is_err());
+    ///
+    /// let s = Dialect::EventBridge.parse("cron(0 12 ? * MON-FRI 2030)").unwrap();
+    /// assert_eq!(s.expression.schedule(), "0 12 * * 1-5");
+    /// assert_eq!(s.years, Some(vec![2030]));
+    /// ```
+    pub fn parse(&self, spec: &str) -> Result<Schedule, String> {
+        self.parse_with_seed(spec, "")
+    }
+
+    /// Returns the schedule of the spec in the dialect with the seed of hashes
+    ///
+    /// The seed is used only by Jenkins, where it is the full name of the job.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cron_gate::dialect::Dialect;
+    ///
+    /// let s = Dialect::Jenkins.parse_with_seed("H H(0-7) * * *", "folder/nightly").unwrap();
+    /// assert_eq!(s.expression.hour_vec.len(), 1);
+    /// assert!(s.expression.hour_vec[0] <= 7);
+    /// ```
+    pub fn parse_with_seed(&self, spec: &str, seed: &str) -> Result<Schedule, String> {
+        let spec = spec.trim();
+        if spec.contains('~') {
+            return Err(format!(
+                "'~' is not supported in {}: '{}'",
+                self.name(),
+                spec
+            ));
+        }
+        match self {
+            Dialect::EventBridge => return parse_event_bridge(spec),
+            Dialect::Jenkins => return parse_jenkins(spec, seed),
+            _ => {}
+        }
+        if spec.starts_with("TZ=") || spec.starts_with("CRON_TZ=") {
+            return Err(if *self == Dialect::Kubernetes {
+                "TZ and CRON_TZ in the schedule are not supported: use spec.timeZone".to_string()
+            } else {
+                format!(
+                    "TZ and CRON_TZ are not supported: {} schedules are in UTC",
+                    self.name()
+                )
+            });
+        }
+
+        let text = match (self, expand_macro(spec)) {
+            (Dialect::Kubernetes, Some(fields)) => fields,
+            _ if spec.starts_with('@') => {
+                return Err(format!("'{}' is not supported in {}", spec, self.name()))
+            }
+            _ => spec,
+        };
+
+        let fields: Vec<&str> = text.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!(
+                "'{}' should have 5 fields in {}, but has {}",
+                spec,
+                self.name(),
+                fields.len()
+            ));
+        }
+        let question = |field: &str| {
+            if *self == Dialect::Kubernetes && field == "?" {
+                "*".to_string()
+            } else {
+                field.to_string()
+            }
+        };
+        let date = question(fields[2]);
+        let month = replace_names(fields[3], &MONTH_NAMES, 1)?;
+        let day = replace_names(&question(fields[4]), &WEEKDAY_NAMES, 0)?;
+        let expression = Expression::new(&format!(
+            "{} {} {} {} {}",
+            fields[0], fields[1], date, month, day
+        ))?;
+
+        if day != "*" && expression.day_vec.contains(&7) {
+            return Err(format!(
+                "Weekday 7 is not supported in {}: use 0 for Sunday",
+                self.name()
+            ));
+        }
+
+        let mut warnings = vec![];
+        if *self == Dialect::GithubActions && runs_within(&expression, 5) {
+            warnings.push(
+                "Runs more often than every 5 minutes, which is the shortest interval of GitHub Actions"
+                    .to_string(),
+            );
+        }
+
+        Ok(Schedule {
+            // As robfig/cron and Vixie cron do, by the text instead of the values,
+            // so that '1-31' is restricted and '*/2' is not
+            day_or: !expression.date.starts_with('*') && !expression.day.starts_with('*'),
+            expression,
+            years: None,
+            warnings,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dialect::Kubernetes => "Kubernetes",
+            Dialect::GithubActions => "GitHub Actions",
+            Dialect::EventBridge => "EventBridge",
+            Dialect::Jenkins => "Jenkins",
+        }
+    }
+}
+
+fn parse_jenkins(spec: &str, seed: &str) -> Result<Schedule, String> {
+    let expression = Expression::new(&jenkins::resolve(spec, seed)?)?;
+    let mut warnings = vec![];
+    if seed.is_empty() && (spec.contains('H') || spec.starts_with('@')) {
+        warnings.push("H is hashed from an empty seed, not the name of a job".to_string());
+    }
+    if runs_every_minute_of_restricted(&expression) {
+        let rest = spec.split_once(' ').map(|(_, rest)| rest).unwrap_or("");
+        warnings.push(format!(
+            "Do you really mean every minute by '{}'? Perhaps you meant 'H {}'",
+            spec, rest
+        ));
+    }
+    Ok(Schedule {
+        expression,
+        day_or: false,
+        years: None,
+        warnings,
+    })
+}
+
+/// Returns true if every minute is on but a higher field is restricted,
+/// as `CronTab.checkSanity` of Jenkins warns
+fn runs_every_minute_of_restricted(e: &Expression) -> bool {
+    let days = (0..7).all(|d| e.day_vec.contains(&d) || (d == 0 && e.day_vec.contains(&7)));
+    e.minute_vec.len() == 60
+        && (e.hour_vec.len() < 24 || e.date_vec.len() < 31 || e.month_vec.len() < 12 || !days)
+}
+
+/// Parses `cron(Minutes Hours Day-of-month Month Day-of-week Year)` or `rate(Value Unit)`
+fn parse_event_bridge(spec: &str) -> Result<Schedule, String> {
+    if let Some(body) = strip_call(spec, "rate") {
+        return parse_rate(body);
+    }
+
+    let body = strip_call(spec, "cron").unwrap_or(spec);
+    let fields: Vec<&str> = body.split_whitespace().collect();
+    if fields.len() != 6 {
+        return Err(format!(
+            "'{}' should have 6 fields in EventBridge, but has {}",
+            spec,
+            fields.len()
+        ));
+    }
+    let (date, day) = (fields[2], fields[4]);
+    if (date == "?") == (day == "?") {
+        return Err(format!(
+            "'{}' should have '?' in either day-of-month or day-of-week",
+            spec
+        ));
+    }
+    if date.contains(&['L', 'W'][..]) {
+        return Err(format!(
+            "Day-of-month '{}' cannot be expressed in cron: 'L' and 'W' are not supported",
+            date
+        ));
+    }
+    if day.contains('#') || day.split(',').any(|d| d.ends_with('L')) {
+        return Err(format!(
+            "Day-of-week '{}' cannot be expressed in cron: '#' and 'L' are not supported",
+            day
+        ));
+    }
+
+    let date = if date == "?" {
+        "*".to_string()
+    } else {
+        expand_step(date, 31)
+    };
+    let month = expand_step(&replace_names(fields[3], &MONTH_NAMES, 1)?, 12);
+    // Weekdays are from 1 for Sunday to 7
+    let day = if day == "?" || day == "*" {
+        "*".to_string()
+    } else {
+        let days = parse_block(
+            &expand_step(&replace_names(day, &WEEKDAY_NAMES, 1)?, 7),
+            1,
+            7,
+        )
+        .map_err(|e| format!("Error on day-of-week: '{}'\n{}", day, e))?;
+        let days: Vec<u32> = days.iter().map(|d| d - 1).collect();
+        format_values(&days, 0, 6)
+    };
+    let years = if fields[5] == "*" {
+        None
+    } else {
+        let years = parse_block(&expand_step(fields[5], 2199), 1970, 2199)
+            .map_err(|e| format!("Error on year: '{}'\n{}", fields[5], e))?;
+        Some(years.iter().map(|y| *y as i32).collect())
+    };
+
+    let expression = Expression::new(&format!(
+        "{} {} {} {} {}",
+        expand_step(fields[0], 59),
+        expand_step(fields[1], 23),
+        date,
+        month,
+        day
+    ))?;
+    Ok(Schedule {
+        expression,
+        day_or: false,
+        years,
+        warnings: vec![],
+    })
+}
+
+/// Parses `5 minutes`, `1 hour` or `1 day`
+fn parse_rate(body: &str) -> Result<Schedule, String> {
+    let parts: Vec<&str> = body.split_whitespace().collect();
+    let value = match parts.first().and_then(|v| v.parse::<u32>().ok()) {
+        Some(v) if v > 0 && parts.len() == 2 => v,
+        _ => {
+            return Err(format!(
+                "Invalid rate '{}': should be like 'rate(5 minutes)'",
+                body
+            ))
+        }
+    };
+    let unit = parts[1];
+    let singular = unit.trim_end_matches('s');
+    if (value == 1) != (unit == singular) {
+        return Err(format!(
+            "Invalid rate '{}': the unit should be singular only for 1",
+            body
+        ));
+    }
+
+    let (text, period) = match singular {
+        "minute" if 60 % value == 0 => (format!("*/{} * * * *", value), "hour"),
+        "hour" if 24 % value == 0 => (format!("0 */{} * * *", value), "day"),
+        "day" if value == 1 => ("0 0 * * *".to_string(), "day"),
+        "minute" | "hour" | "day" => {
+            return Err(format!(
+                "rate({}) cannot be expressed in cron: it does not divide a {}",
+                body,
+                if singular == "minute" { "hour" } else { "day" }
+            ))
+        }
+        _ => {
+            return Err(format!(
+                "Invalid unit '{}' of rate: should be minutes, hours or days",
+                unit
+            ))
+        }
+    };
+    Ok(Schedule {
+        expression: Expression::new(&text)?,
+        day_or: false,
+        years: None,
+        warnings: vec![format!(
+            "rate() counts from when the rule is created, but it is aligned to the start of the {} here",
+            period
+        )],
+    })
+}
+
+/// Returns the body of `name(body)`
+fn strip_call<'a>(spec: &'a str, name: &str) -> Option<&'a str> {
+    spec.strip_prefix(name)?
+        .trim_start()
+        .strip_prefix('(')?
+        .strip_suffix(')')
+        .map(|body| body.trim())
+}
+
+/// Replaces 'a/b' meaning from 'a' every 'b' with 'a-max/b'
+fn expand_step(field: &str, max: u32) -> String {
+    field
+        .split(',')
+        .map(|unit| match unit.find('/') {
+            Some(index) if unit[..index].bytes().all(|b| b.is_ascii_digit()) => {
+                format!("{}-{}{}", &unit[..index], max, &unit[index..])
+            }
+            _ => unit.to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Returns the fields of a macro like `@daily`, or None for `@reboot` and others
+pub(crate) fn expand_macro(spec: &str) -> Option<&'static str> {
+    match spec {
+        "@yearly" | "@annually" => Some("0 0 1 1 *"),
+        "@monthly" => Some("0 0 1 * *"),
+        "@weekly" => Some("0 0 * * 0"),
+        "@daily" | "@midnight" => Some("0 0 * * *"),
+        "@hourly" => Some("0 * * * *"),
+        _ => None,
+    }
+}
+
+/// Replaces names like 'JAN' and 'mon' with their numbers
+pub(crate) fn replace_names(field: &str, names: &[&str], offset: usize) -> Result<String, String> {
+    let mut ret = String::new();
+    let mut word = String::new();
+    for c in field.chars().chain(std::iter::once(' ')) {
+        if c.is_ascii_alphabetic() {
+            word.push(c);
+            continue;
+        }
+        if !word.is_empty() {
+            let upper = word.to_uppercase();
+            let index = names
+                .iter()
+                .position(|n| *n == upper)
+                .ok_or_else(|| format!("Invalid name '{}': should be like '{}'", word, names[0]))?;
+            ret.push_str(&(index + offset).to_string());
+            word.clear();
+        }
+        if c != ' ' {
+            ret.push(c);
+        }
+    }
+    Ok(ret)
+}
+
+/// Returns true if two runs can be within the minutes
+fn runs_within(expression: &Expression, minutes: u32) -> bool {
+    let m = &expression.minute_vec;
+    let consecutive_hours =
+        expression.hour_vec.windows(2).any(|w| w[1] == w[0] + 1) || expression.hour_vec.len() == 24;
+    m.windows(2).any(|w| w[1] - w[0] < minutes)
+        || (consecutive_hours && m[0] + 60 - m[m.len() - 1] < minutes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn test_parse_kubernetes() {
+        let parse = |spec| {
+            Dialect::Kubernetes
+                .parse(spec)
+                .unwrap()
+                .expression
+                .schedule()
+        };
+        assert_eq!(parse("@midnight"), "0 0 * * *");
+        assert_eq!(parse("0 0 ? * sun"), "0 0 * * 0");
+        assert_eq!(parse("*/30 9-17 * * MON-FRI"), "*/30 9-17 * * 1-5");
+        assert!(Dialect::Kubernetes.parse("@every 1h").is_err());
+        assert!(Dialect::Kubernetes.parse("0 0 * * * command").is_err());
+        assert!(Dialect::Kubernetes.parse("0 0 * * 7").is_err());
+        assert!(Dialect::Kubernetes.parse("0 0 * FOO *").is_err());
+    }
+
+    #[test]
+    fn test_parse_github_actions() {
+        let parse = |spec| Dialect::GithubActions.parse(spec).unwrap();
+        assert!(parse("*/15 * * * *").warnings.is_empty());
+        assert_eq!(parse("*/2 * * * *").warnings.len(), 1);
+        assert_eq!(parse("0,58 * * * *").warnings.len(), 1);
+        assert!(parse("0,58 9,12 * * *").warnings.is_empty());
+        assert!(Dialect::GithubActions.parse("0 0 ? * *").is_err());
+        assert!(Dialect::GithubActions
+            .parse("TZ=Asia/Tokyo 0 0 * * *")
+            .is_err());
+    }
+
+    #[test]
+    fn test_day_or() {
+        assert!(Dialect::Kubernetes.parse("0 0 1 * 1").unwrap().day_or);
+        assert!(!Dialect::Kubernetes.parse("0 0 1 * *").unwrap().day_or);
+        assert!(!Dialect::Kubernetes.parse("0 0 * * 1").unwrap().day_or);
+        assert!(!Dialect::Kubernetes.parse("0 0 ? * 1").unwrap().day_or);
+        assert!(Dialect::Kubernetes.parse("0 0 1-31 * 1").unwrap().day_or);
+        assert!(!Dialect::Kubernetes.parse("0 0 */2 * 1").unwrap().day_or);
+
+        let from = Utc.ymd(2019, 9, 28).and_hms(0, 0, 0);
+        let s = Dialect::Kubernetes.parse("0 0 1-31 * 1").unwrap();
+        assert_eq!(
+            s.earler_excuting_datetimes(from, 2),
+            [
+                Utc.ymd(2019, 9, 28).and_hms(0, 0, 0),
+                Utc.ymd(2019, 9, 29).and_hms(0, 0, 0),
+            ]
+        );
+        // Odd dates which are Mondays
+        let s = Dialect::Kubernetes.parse("0 0 */2 * 1").unwrap();
+        assert_eq!(
+            s.earler_excuting_datetimes(from, 2),
+            [
+                Utc.ymd(2019, 10, 7).and_hms(0, 0, 0),
+                Utc.ymd(2019, 10, 21).and_hms(0, 0, 0),
+            ]
+        );
+
+        let s = Dialect::GithubActions.parse("0 0 1 * 1").unwrap();
+        assert_eq!(
+            s.earler_excuting_datetimes(from, 3),
+            [
+                Utc.ymd(2019, 9, 30).and_hms(0, 0, 0),
+                Utc.ymd(2019, 10, 1).and_hms(0, 0, 0),
+                Utc.ymd(2019, 10, 7).and_hms(0, 0, 0),
+            ]
+        );
+        assert_eq!(
+            s.previous_datetimes(Utc.ymd(2019, 10, 7).and_hms(0, 0, 0), 2),
+            [
+                Utc.ymd(2019, 10, 1).and_hms(0, 0, 0),
+                Utc.ymd(2019, 9, 30).and_hms(0, 0, 0),
+            ]
+        );
+        assert_eq!(
+            s.datetimes_between(from, Utc.ymd(2019, 10, 7).and_hms(0, 0, 0), 10),
+            [
+                Utc.ymd(2019, 9, 30).and_hms(0, 0, 0),
+                Utc.ymd(2019, 10, 1).and_hms(0, 0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_event_bridge() {
+        let parse = |spec| Dialect::EventBridge.parse(spec).unwrap();
+        assert_eq!(parse("0 12 * * ? *").expression.schedule(), "0 12 * * *");
+        assert_eq!(
+            parse("cron(0/15 * ? * 2-6 *)").expression.schedule(),
+            "0-59/15 * * * 1-5"
+        );
+        assert_eq!(
+            parse("cron(0 8 1 JAN,JUL ? *)").expression.month_vec,
+            vec![1, 7]
+        );
+        assert_eq!(
+            parse("cron(0 0 ? * SUN,7 *)").expression.day_vec,
+            vec![0, 6]
+        );
+        assert_eq!(
+            parse("cron(0 0 1 1 ? 2030-2032)").years,
+            Some(vec![2030, 2031, 2032])
+        );
+
+        let error = |spec| Dialect::EventBridge.parse(spec).is_err();
+        assert!(error("cron(0 12 * * * *)"));
+        assert!(error("cron(0 12 ? * ? *)"));
+        assert!(error("cron(0 12 * * ?)"));
+        assert!(error("cron(0 12 L * ? *)"));
+        assert!(error("cron(0 12 15W * ? *)"));
+        assert!(error("cron(0 12 ? * 6#3 *)"));
+        assert!(error("cron(0 12 ? * 6L *)"));
+        assert!(error("cron(0 12 ? * 0 *)"));
+        assert!(error("cron(0 12 * * ? 1969)"));
+    }
+
+    #[test]
+    fn test_parse_rate() {
+        let parse = |spec| Dialect::EventBridge.parse(spec).unwrap();
+        assert_eq!(
+            parse("rate(5 minutes)").expression.schedule(),
+            "*/5 * * * *"
+        );
+        assert_eq!(parse("rate(1 hour)").expression.schedule(), "0 */1 * * *");
+        assert_eq!(parse("rate( 1 day )").expression.schedule(), "0 0 * * *");
+        assert_eq!(parse("rate(6 hours)").warnings.len(), 1);
+
+        let error = |spec| Dialect::EventBridge.parse(spec).is_err();
+        assert!(error("rate(1 minutes)"));
+        assert!(error("rate(5 minute)"));
+        assert!(error("rate(7 minutes)"));
+        assert!(error("rate(2 days)"));
+        assert!(error("rate(0 hours)"));
+        assert!(error("rate(1 week)"));
+    }
+
+    #[test]
+    fn test_parse_jenkins() {
+        let s = Dialect::Jenkins
+            .parse_with_seed("H H(0-7) H H *", "job")
+            .unwrap();
+        assert_eq!(s.expression.schedule(), "31 7 8 12 *");
+        assert!(s.warnings.is_empty());
+        assert!(!s.day_or);
+
+        assert_eq!(Dialect::Jenkins.parse("@daily").unwrap().warnings.len(), 1);
+        let warnings = |spec| {
+            Dialect::Jenkins
+                .parse_with_seed(spec, "job")
+                .unwrap()
+                .warnings
+        };
+        assert_eq!(
+            warnings("* 9 * * 1-5"),
+            ["Do you really mean every minute by '* 9 * * 1-5'? Perhaps you meant 'H 9 * * 1-5'"]
+        );
+        assert_eq!(warnings("*/1 * 1 * *").len(), 1);
+        assert!(warnings("* * * * *").is_empty());
+        assert!(warnings("*/15 * * * *").is_empty());
+        assert!(warnings("*/15 9 * * *").is_empty());
+        assert!(warnings("0-59 * * * 0-6").is_empty());
+        assert!(Dialect::Jenkins.parse("0 0 * * * command").is_err());
+        assert!(Dialect::Jenkins.parse("0~30 * * * *").is_err());
+    }
+
+    #[test]
+    fn test_years() {
+        let s = Dialect::EventBridge
+            .parse("cron(0 0 1 1 ? 2020,2030)")
+            .unwrap();
+        let from = Utc.ymd(2019, 5, 28).and_hms(0, 0, 0);
+        assert_eq!(
+            s.earler_excuting_datetimes(from, 3),
+            [
+                Utc.ymd(2020, 1, 1).and_hms(0, 0, 0),
+                Utc.ymd(2030, 1, 1).and_hms(0, 0, 0),
+            ]
+        );
+
+        let s = Dialect::EventBridge.parse("cron(0 0 * * ? 2019)").unwrap();
+        let from = Utc.ymd(2019, 12, 30).and_hms(0, 0, 0);
+        assert_eq!(s.earler_excuting_datetimes(from, 5).len(), 2);
+
+        let s = Dialect::EventBridge.parse("cron(* * * * ? 2030)").unwrap();
+        let to = Utc.ymd(2031, 1, 1).and_hms(0, 0, 0);
+        assert_eq!(
+            s.datetimes_between(from, to, 2),
+            [
+                Utc.ymd(2030, 1, 1).and_hms(0, 0, 0),
+                Utc.ymd(2030, 1, 1).and_hms(0, 1, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expand_step() {
+        assert_eq!(expand_step("0/15", 59), "0-59/15");
+        assert_eq!(expand_step("*/15,5-10/2,3", 59), "*/15,5-10/2,3");
+    }
+
+    #[test]
+    fn test_replace_names() {
+        assert_eq!(
+            replace_names("jan-Mar,DEC", &MONTH_NAMES, 1).unwrap(),
+            "1-3,12"
+        );
+        assert_eq!(replace_names("*/2", &MONTH_NAMES, 1).unwrap(), "*/2");
+        assert!(replace_names("JANUARY", &MONTH_NAMES, 1).is_err());
+    }
+}
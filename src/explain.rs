@@ -0,0 +1,156 @@
+//! Table of how each field of an expression expands
+
+use crate::expression::{parse_random_range, parse_unit, Expression};
+
+/// Returns a table of the text, the values and the count of each field
+/// with notes on how steps are anchored
+///
+/// A step counts from the start of its range, or of the field for `*`,
+/// not from multiples of the step.
+///
+/// # Examples
+///
+/// ```
+/// use cron_gate::explain::explain;
+/// use cron_gate::expression::Expression;
+///
+/// let table = explain(&Expression::new("*/20 9 * * 1-5 command").unwrap());
+/// let minute = table.lines().nth(1).unwrap();
+/// assert!(minute.starts_with("minute  */20  0,20,40"));
+/// assert!(minute.ends_with("3      '*/20' steps by 20 from 0, the start of the field, to 40"));
+/// ```
+pub fn explain(expression: &Expression) -> String {
+    let rows: Vec<[String; 5]> = [
+        ("minute", &expression.minute, &expression.minute_vec, 0, 59),
+        ("hour", &expression.hour, &expression.hour_vec, 0, 23),
+        ("date", &expression.date, &expression.date_vec, 1, 31),
+        ("month", &expression.month, &expression.month_vec, 1, 12),
+        ("day", &expression.day, &expression.day_vec, 0, 7),
+    ]
+    .iter()
+    .map(|(name, raw, values, min, max)| {
+        let mut notes: Vec<String> = raw
+            .split(',')
+            .filter_map(|unit| note(unit, *min, *max, values))
+            .collect();
+        if *name == "day" && values.contains(&7) {
+            notes.push("7 is Sunday as 0".to_string());
+        }
+        let texts: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+        [
+            name.to_string(),
+            raw.to_string(),
+            texts.join(","),
+            values.len().to_string(),
+            notes.join("; "),
+        ]
+    })
+    .collect();
+    table(&["field", "text", "values", "count", "notes"], &rows)
+}
+
+/// Returns a note on the unit having a step or `~`
+///
+/// The value of `~` is taken from the values of the field, which the seed
+/// of the expression chose.
+fn note(unit: &str, min: u32, max: u32, values: &[u32]) -> Option<String> {
+    if let Some(index) = unit.find('~') {
+        let (from, to) = parse_random_range(&unit[..index], &unit[(index + 1)..], min, max).ok()?;
+        let chosen: Vec<String> = values
+            .iter()
+            .filter(|v| from <= **v && **v <= to)
+            .map(|v| v.to_string())
+            .collect();
+        return Some(format!(
+            "'{}' is {} chosen by the seed",
+            unit,
+            chosen.join(" or ")
+        ));
+    }
+    let index = unit.find('/')?;
+    let values = parse_unit(unit, min, max).ok()?;
+    let start = if unit.starts_with('*') {
+        "the field"
+    } else {
+        "the range"
+    };
+    Some(format!(
+        "'{}' steps by {} from {}, the start of {}, to {}",
+        unit,
+        &unit[(index + 1)..],
+        values[0],
+        start,
+        values[values.len() - 1]
+    ))
+}
+
+/// Returns rows aligned in columns separated by two spaces
+fn table(header: &[&str; 5], rows: &[[String; 5]]) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|h| h.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let line = |cells: Vec<&str>| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        padded.join("  ").trim_end().to_string() + "\n"
+    };
+    let mut ret = line(header.to_vec());
+    for row in rows {
+        ret += &line(row.iter().map(|c| c.as_str()).collect());
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain() {
+        let e = Expression::new("5-59/10 */4 1,15 * 1-5 command").unwrap();
+        assert_eq!(
+            explain(&e),
+            "field   text     values                      count  notes\n\
+             minute  5-59/10  5,15,25,35,45,55            6      \
+             '5-59/10' steps by 10 from 5, the start of the range, to 55\n\
+             hour    */4      0,4,8,12,16,20              6      \
+             '*/4' steps by 4 from 0, the start of the field, to 20\n\
+             date    1,15     1,15                        2\n\
+             month   *        1,2,3,4,5,6,7,8,9,10,11,12  12\n\
+             day     1-5      1,2,3,4,5                   5\n"
+        );
+    }
+
+    #[test]
+    fn test_explain_with_seed() {
+        let e = Expression::with_seed("0~59 * * * * command", 7).unwrap();
+        let table = explain(&e);
+        let minute = table.lines().nth(1).unwrap();
+        assert!(minute.ends_with(&format!("'0~59' is {} chosen by the seed", e.minute_vec[0])));
+    }
+
+    #[test]
+    fn test_note() {
+        assert_eq!(note("5", 0, 59, &[5]), None);
+        assert_eq!(note("1-5", 0, 59, &[1, 2, 3, 4, 5]), None);
+        assert_eq!(
+            note("10-22/5", 0, 23, &[10, 15, 20]),
+            Some("'10-22/5' steps by 5 from 10, the start of the range, to 20".to_string())
+        );
+        assert_eq!(
+            note("~5", 0, 59, &[3, 30]),
+            Some("'~5' is 3 chosen by the seed".to_string())
+        );
+        // The value is the same as another unit
+        assert_eq!(
+            note("0~9", 0, 59, &[5, 7]),
+            Some("'0~9' is 5 or 7 chosen by the seed".to_string())
+        );
+    }
+}
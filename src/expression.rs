@@ -1,29 +1,59 @@
 extern crate chrono;
-extern crate regex;
 
+use crate::compact::{format_values, CompactExpression};
 use chrono::offset::TimeZone;
-use chrono::{DateTime, Datelike, Duration, Local, ParseError, Timelike, Weekday};
-use regex::Captures;
-use regex::Regex;
-use std::collections::HashSet;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDateTime, ParseError, Timelike, Weekday};
 use std::fmt;
 
 pub const DATE_FORMAT: &str = "%Y/%m/%d %H:%M";
 
+pub(crate) const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CronLine {
     pub datetime: DateTime<Local>,
     pub command: String,
 }
 
+impl CronLine {
+    /// Returns the datetime in the strftime format followed by the command
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::Local;
+    /// use chrono::offset::TimeZone;
+    /// use cron_gate::expression::CronLine;
+    ///
+    /// let line = CronLine {
+    ///     datetime: Local.datetime_from_str("2019/5/28 9:0", "%Y/%m/%d %H:%M").unwrap(),
+    ///     command: "command".to_string(),
+    /// };
+    /// assert_eq!(line.format("%a %d %b %H:%M"), "Tue 28 May 09:00 command");
+    /// assert_eq!(line.format("%Y/%m/%d %H:%M"), line.to_string());
+    /// ```
+    pub fn format(&self, datetime_format: &str) -> String {
+        format!("{} {}", self.datetime.format(datetime_format), self.command)
+    }
+}
+
 impl fmt::Display for CronLine {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "{} {}",
-            self.datetime.format("%Y/%m/%d %H:%M"),
-            self.command
-        )
+        write!(f, "{}", self.format(DATE_FORMAT))
     }
 }
 
@@ -42,13 +72,41 @@ pub struct Expression {
     pub day_vec: Vec<u32>,
 }
 
+/// The alternate form `{:#}` reports values chosen for `~` like `0~30=17`
+///
+/// # Examples
+///
+/// ```
+/// use cron_gate::expression::Expression;
+///
+/// let e = Expression::with_seed("0~30 9 * * * command", 42).unwrap();
+/// assert_eq!(e.to_string(), "0~30 9 * * * command");
+/// assert_eq!(format!("{:#}", e), format!("0~30={} 9 * * * command", e.minute_vec[0]));
+/// ```
 impl fmt::Display for Expression {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "{} {} {} {} {} {}",
-            self.minute, self.hour, self.date, self.month, self.day, self.command
-        )
+        if !f.alternate() {
+            return write!(
+                f,
+                "{} {} {} {} {} {}",
+                self.minute, self.hour, self.date, self.month, self.day, self.command
+            );
+        }
+        let fields = [
+            (&self.minute, &self.minute_vec, 0, 59),
+            (&self.hour, &self.hour_vec, 0, 23),
+            (&self.date, &self.date_vec, 1, 31),
+            (&self.month, &self.month_vec, 1, 12),
+            (&self.day, &self.day_vec, 0, 7),
+        ];
+        for (raw, vec, min, max) in fields.iter() {
+            if raw.contains('~') {
+                write!(f, "{}={} ", raw, format_values(vec, *min, *max))?;
+            } else {
+                write!(f, "{} ", raw)?;
+            }
+        }
+        write!(f, "{}", self.command)
     }
 }
 
@@ -76,31 +134,47 @@ impl Expression {
     /// });
     /// ```
     pub fn new(expression_str: &str) -> Result<Expression, String> {
+        Expression::with_seed(expression_str, 0)
+    }
+
+    /// Returns a Expression whose `~` values are chosen by the seed
+    ///
+    /// The same seed always chooses the same values, see `parse_unit_with_seed`.
+    /// `Expression::new` uses the seed `0`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cron_gate::expression::Expression;
+    ///
+    /// let e = Expression::with_seed("0~30 * * * * command", 42).unwrap();
+    /// assert!(e.minute_vec[0] <= 30);
+    /// assert_eq!(e, Expression::with_seed("0~30 * * * * command", 42).unwrap());
+    /// assert_eq!(e.minute, "0~30");
+    /// ```
+    pub fn with_seed(expression_str: &str, seed: u64) -> Result<Expression, String> {
         let spw: Vec<&str> = expression_str.split_whitespace().collect();
 
         if spw.len() < 5 {
             return Err(format!("Invalid expression: {}", expression_str));
         }
 
-        let mut command = "[command]".to_string();
-        for i in 5..spw.len() {
-            if i == 5 {
-                command = spw[i].to_string();
-            } else {
-                command = format!("{} {}", command, spw[i]);
-            }
-        }
+        let command = if spw.len() > 5 {
+            spw[5..].join(" ")
+        } else {
+            "[command]".to_string()
+        };
 
-        let minute_vec = parse_block(spw[0], 0, 59)
+        let minute_vec = parse_block_with_seed(spw[0], 0, 59, seed)
             .map_err(|e| format!("Error on minute: {}\n{}", spw[0], e))?;
-        let hour_vec = parse_block(spw[1], 0, 23)
+        let hour_vec = parse_block_with_seed(spw[1], 0, 23, seed)
             .map_err(|e| format!("Error on hour: '{}'\n{}", spw[1], e))?;
-        let date_vec = parse_block(spw[2], 1, 31)
+        let date_vec = parse_block_with_seed(spw[2], 1, 31, seed)
             .map_err(|e| format!("Error on date: '{}'\n{}", spw[2], e))?;
-        let month_vec = parse_block(spw[3], 1, 12)
+        let month_vec = parse_block_with_seed(spw[3], 1, 12, seed)
             .map_err(|e| format!("Error on month: '{}'\n{}", spw[3], e))?;
-        let day_vec =
-            parse_block(spw[4], 0, 7).map_err(|e| format!("Error on day: '{}'\n{}", spw[4], e))?;
+        let day_vec = parse_block_with_seed(spw[4], 0, 7, seed)
+            .map_err(|e| format!("Error on day: '{}'\n{}", spw[4], e))?;
 
         Ok(Expression {
             minute: spw[0].to_string(),
@@ -108,7 +182,7 @@ impl Expression {
             date: spw[2].to_string(),
             month: spw[3].to_string(),
             day: spw[4].to_string(),
-            command: command,
+            command,
             minute_vec,
             hour_vec,
             date_vec,
@@ -117,6 +191,95 @@ impl Expression {
         })
     }
 
+    /// Returns the five fields without the command
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cron_gate::expression::Expression;
+    ///
+    /// let e = Expression::new("0 9 * * 1-5 echo 'Yo'").unwrap();
+    /// assert_eq!(e.schedule(), "0 9 * * 1-5");
+    /// ```
+    pub fn schedule(&self) -> String {
+        format!(
+            "{} {} {} {} {}",
+            self.minute, self.hour, self.date, self.month, self.day
+        )
+    }
+
+    /// Returns true if the expression fires at least once
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cron_gate::expression::Expression;
+    ///
+    /// assert!(Expression::new("0 0 29 2 * command").unwrap().is_satisfiable());
+    /// assert!(!Expression::new("0 0 30 2 * command").unwrap().is_satisfiable());
+    /// ```
+    pub fn is_satisfiable(&self) -> bool {
+        self.never_fires_reason().is_none()
+    }
+
+    /// Returns why the expression never fires, or None if it fires
+    ///
+    /// Every existing month/date pair falls on each weekday sooner or later,
+    /// so only the month/date combination can make an expression impossible.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cron_gate::expression::Expression;
+    ///
+    /// let e = Expression::new("0 0 31 2,4 * command").unwrap();
+    /// assert_eq!(
+    ///     e.never_fires_reason().unwrap(),
+    ///     "Never fires: date '31' does not exist in February (29 days at most), April (30 days at most)"
+    /// );
+    /// assert_eq!(Expression::new("0 0 31 2,5 * command").unwrap().never_fires_reason(), None);
+    /// ```
+    pub fn never_fires_reason(&self) -> Option<String> {
+        let fields = [
+            ("minute", &self.minute, &self.minute_vec),
+            ("hour", &self.hour, &self.hour_vec),
+            ("date", &self.date, &self.date_vec),
+            ("month", &self.month, &self.month_vec),
+            ("day", &self.day, &self.day_vec),
+        ];
+        for (name, raw, vec) in fields.iter() {
+            if vec.is_empty() {
+                return Some(format!("Never fires: {} '{}' matches no value", name, raw));
+            }
+        }
+
+        let smallest_date = self.date_vec[0];
+        if self
+            .month_vec
+            .iter()
+            .any(|m| smallest_date <= max_days_in_month(*m))
+        {
+            return None;
+        }
+
+        let months: Vec<String> = self
+            .month_vec
+            .iter()
+            .map(|m| {
+                format!(
+                    "{} ({} days at most)",
+                    MONTH_NAMES[(*m - 1) as usize],
+                    max_days_in_month(*m)
+                )
+            })
+            .collect();
+        Some(format!(
+            "Never fires: date '{}' does not exist in {}",
+            self.date,
+            months.join(", ")
+        ))
+    }
+
     /// Returns a vec of indexes of the datetime earliest from
     ///
     /// # Examples
@@ -139,12 +302,12 @@ impl Expression {
         ret
     }
 
-    /// Returns earler datetimes from
+    /// Returns earler datetimes from in the time zone of `from`
     ///
     /// # Examples
     ///
     /// ```
-    /// use chrono::Local;
+    /// use chrono::{Local, Utc};
     /// use chrono::offset::TimeZone;
     /// use cron_gate::expression::Expression;
     ///
@@ -154,13 +317,171 @@ impl Expression {
     ///   Local.datetime_from_str("2019/5/28 9:0", "%Y/%m/%d %H:%M").unwrap(),
     ///   Local.datetime_from_str("2019/5/29 9:0", "%Y/%m/%d %H:%M").unwrap(),
     /// ]);
+    ///
+    /// let from = Utc.ymd(2019, 5, 28).and_hms(0, 0, 0);
+    /// assert_eq!(e.earler_excuting_datetimes(from, 1), [Utc.ymd(2019, 5, 28).and_hms(9, 0, 0)]);
+    /// ```
+    pub fn earler_excuting_datetimes<Tz: TimeZone>(
+        &self,
+        from: DateTime<Tz>,
+        count: usize,
+    ) -> Vec<DateTime<Tz>> {
+        let mut ret: Vec<DateTime<Tz>> = vec![];
+        if !self.is_satisfiable() {
+            return ret;
+        }
+
+        let compact = CompactExpression::from(self);
+        let mut current = truncate_to_minute(from.naive_local());
+        while ret.len() < count {
+            match compact.next_from(current) {
+                Some(naive) => {
+                    // Local times skipped or repeated by DST are not unique
+                    if let Some(datetime) = from.timezone().from_local_datetime(&naive).single() {
+                        ret.push(datetime);
+                    }
+                    // The last minute chrono supports has no next one
+                    match naive.checked_add_signed(Duration::minutes(1)) {
+                        Some(next) => current = next,
+                        None => break,
+                    }
+                }
+                None => break,
+            }
+        }
+        ret
+    }
+
+    /// Returns at most `count` datetimes from `from` until `to` (exclusive)
+    /// in the time zone of `from`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::{TimeZone, Utc};
+    /// use cron_gate::expression::Expression;
+    ///
+    /// let e = Expression::new("0 9 * * * command").unwrap();
+    /// let from = Utc.ymd(2019, 5, 28).and_hms(0, 0, 0);
+    /// let to = Utc.ymd(2019, 5, 30).and_hms(9, 0, 0);
+    /// assert_eq!(e.datetimes_between(from.clone(), to.clone(), 10), [
+    ///     Utc.ymd(2019, 5, 28).and_hms(9, 0, 0),
+    ///     Utc.ymd(2019, 5, 29).and_hms(9, 0, 0),
+    /// ]);
+    /// assert_eq!(e.datetimes_between(from, to, 1), [Utc.ymd(2019, 5, 28).and_hms(9, 0, 0)]);
+    /// ```
+    pub fn datetimes_between<Tz: TimeZone>(
+        &self,
+        from: DateTime<Tz>,
+        to: DateTime<Tz>,
+        count: usize,
+    ) -> Vec<DateTime<Tz>> {
+        let mut ret: Vec<DateTime<Tz>> = vec![];
+        if !self.is_satisfiable() {
+            return ret;
+        }
+
+        let compact = CompactExpression::from(self);
+        let mut current = truncate_to_minute(from.naive_local());
+        while ret.len() < count {
+            let naive = match compact.next_from(current) {
+                Some(naive) => naive,
+                None => break,
+            };
+            if let Some(datetime) = from.timezone().from_local_datetime(&naive).single() {
+                if datetime >= to {
+                    break;
+                }
+                ret.push(datetime);
+            }
+            match naive.checked_add_signed(Duration::minutes(1)) {
+                Some(next) => current = next,
+                None => break,
+            }
+        }
+        ret
+    }
+
+    /// Returns datetimes before `before` in the time zone of it, the latest first
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::{TimeZone, Utc};
+    /// use cron_gate::expression::Expression;
+    ///
+    /// let e = Expression::new("0 9 27-29 5 * command").unwrap();
+    /// let before = Utc.ymd(2019, 5, 28).and_hms(9, 0, 0);
+    /// assert_eq!(e.previous_datetimes(before, 2), [
+    ///     Utc.ymd(2019, 5, 27).and_hms(9, 0, 0),
+    ///     Utc.ymd(2018, 5, 29).and_hms(9, 0, 0),
+    /// ]);
+    /// ```
+    pub fn previous_datetimes<Tz: TimeZone>(
+        &self,
+        before: DateTime<Tz>,
+        count: usize,
+    ) -> Vec<DateTime<Tz>> {
+        let mut ret: Vec<DateTime<Tz>> = vec![];
+        if !self.is_satisfiable() {
+            return ret;
+        }
+
+        let compact = CompactExpression::from(self);
+        let mut current = match truncate_to_minute(before.naive_local())
+            .checked_sub_signed(Duration::minutes(1))
+        {
+            Some(current) => current,
+            None => return ret,
+        };
+        while ret.len() < count {
+            match compact.prev_from(current) {
+                Some(naive) => {
+                    if let Some(datetime) = before.timezone().from_local_datetime(&naive).single() {
+                        ret.push(datetime);
+                    }
+                    match naive.checked_sub_signed(Duration::minutes(1)) {
+                        Some(previous) => current = previous,
+                        None => break,
+                    }
+                }
+                None => break,
+            }
+        }
+        ret
+    }
+
+    /// Returns earler datetimes from by scanning every combination of the fields
+    ///
+    /// This is the former implementation of `earler_excuting_datetimes`,
+    /// kept to compare with it in benchmarks and tests.
+    /// It gives up after `4 * count` years.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::Local;
+    /// use chrono::offset::TimeZone;
+    /// use cron_gate::expression::Expression;
+    ///
+    /// let e = Expression::new("0 9 27-29 5 * command").unwrap();
+    /// let from = Local.datetime_from_str("2019/5/28 0:0", "%Y/%m/%d %H:%M").unwrap();
+    /// assert_eq!(e.earler_excuting_datetimes_by_scan(from, 2), [
+    ///   Local.datetime_from_str("2019/5/28 9:0", "%Y/%m/%d %H:%M").unwrap(),
+    ///   Local.datetime_from_str("2019/5/29 9:0", "%Y/%m/%d %H:%M").unwrap(),
+    /// ]);
     /// ```
-    pub fn earler_excuting_datetimes(
+    #[doc(hidden)]
+    pub fn earler_excuting_datetimes_by_scan(
         &self,
         from: DateTime<Local>,
         count: usize,
     ) -> Vec<DateTime<Local>> {
         let mut ret: Vec<DateTime<Local>> = vec![];
+        if !self.is_satisfiable() {
+            return ret;
+        }
+
         let mut indexes = self.earliest_date_time_index(from);
 
         for year in (from.year() as i64)..((from.year() as i64) + 4 * (count as i64)) {
@@ -249,6 +570,61 @@ impl Expression {
         }
         vec
     }
+
+    /// Returns earler CronLines from, or the reason why the expression never fires
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::Local;
+    /// use chrono::offset::TimeZone;
+    /// use cron_gate::expression::Expression;
+    ///
+    /// let from = Local.datetime_from_str("2019/5/28 0:0", "%Y/%m/%d %H:%M").unwrap();
+    ///
+    /// let e = Expression::new("0 9 27-29 5 * command").unwrap();
+    /// assert_eq!(e.try_executing_dates(from, 2).unwrap().len(), 2);
+    ///
+    /// let e = Expression::new("0 0 30 2 * command").unwrap();
+    /// assert_eq!(
+    ///     e.try_executing_dates(from, 2).unwrap_err(),
+    ///     "Never fires: date '30' does not exist in February (29 days at most)"
+    /// );
+    /// ```
+    pub fn try_executing_dates(
+        &self,
+        after: DateTime<Local>,
+        number: usize,
+    ) -> Result<Vec<CronLine>, String> {
+        match self.never_fires_reason() {
+            Some(reason) => Err(reason),
+            None => Ok(self.executing_dates(after, number)),
+        }
+    }
+}
+
+fn truncate_to_minute(datetime: NaiveDateTime) -> NaiveDateTime {
+    datetime
+        .date()
+        .and_hms(datetime.hour(), datetime.minute(), 0)
+}
+
+pub(crate) fn days_in_month(year: i32, month: u32) -> u32 {
+    let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    if month == 2 && !leap {
+        28
+    } else {
+        max_days_in_month(month)
+    }
+}
+
+/// Returns the number of days of the month in a leap year
+pub(crate) fn max_days_in_month(month: u32) -> u32 {
+    match month {
+        2 => 29,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
 }
 
 fn parse_datetime(
@@ -264,19 +640,19 @@ fn parse_datetime(
     )
 }
 
-fn is_on_weekday(weekday: &Weekday, v: &Vec<u32>) -> bool {
+fn is_on_weekday(weekday: &Weekday, v: &[u32]) -> bool {
     match weekday {
-        Weekday::Sun => v.into_iter().find(|&i| *i == 0 || *i == 7).is_some(),
-        Weekday::Mon => v.into_iter().find(|&i| *i == 1).is_some(),
-        Weekday::Tue => v.into_iter().find(|&i| *i == 2).is_some(),
-        Weekday::Wed => v.into_iter().find(|&i| *i == 3).is_some(),
-        Weekday::Thu => v.into_iter().find(|&i| *i == 4).is_some(),
-        Weekday::Fri => v.into_iter().find(|&i| *i == 5).is_some(),
-        Weekday::Sat => v.into_iter().find(|&i| *i == 6).is_some(),
+        Weekday::Sun => v.iter().any(|i| *i == 0 || *i == 7),
+        Weekday::Mon => v.contains(&1),
+        Weekday::Tue => v.contains(&2),
+        Weekday::Wed => v.contains(&3),
+        Weekday::Thu => v.contains(&4),
+        Weekday::Fri => v.contains(&5),
+        Weekday::Sat => v.contains(&6),
     }
 }
 
-fn get_smalest_index_from(v: &Vec<u32>, from: u32) -> usize {
+fn get_smalest_index_from(v: &[u32], from: u32) -> usize {
     for (index, i) in v.iter().enumerate() {
         if from <= *i {
             return index;
@@ -316,16 +692,68 @@ pub fn get_date_range_between(from: DateTime<Local>, to: DateTime<Local>) -> Vec
 /// assert_eq!(v, vec![1, 2, 4, 5, 6, 8, 11]);
 /// ```
 pub fn parse_block(minute: &str, min: u32, max: u32) -> Result<Vec<u32>, String> {
+    parse_block_with_seed(minute, min, max, 0)
+}
+
+/// Returns numbers parsed from block expression with `~` chosen by the seed
+pub fn parse_block_with_seed(
+    minute: &str,
+    min: u32,
+    max: u32,
+    seed: u64,
+) -> Result<Vec<u32>, String> {
     let mut minutes: Vec<u32> = Vec::new();
     for u in minute.split(',') {
         minutes.append(
-            &mut parse_unit(u, min, max)
+            &mut parse_unit_with_seed(u, min, max, seed)
                 .map_err(|e| format!("Invalid expression on '{}'\n{}", u, e))?,
         );
     }
     Ok(uniq_and_sort(&minutes))
 }
 
+/// Returns numbers of the block with the values of `~` given instead of chosen by a seed,
+/// as the alternate form `{:#}` of `Expression` shows them
+///
+/// It returns an error unless the block can choose the values.
+#[cfg(feature = "serde")]
+pub(crate) fn parse_block_with_chosen(
+    block: &str,
+    min: u32,
+    max: u32,
+    values: &[u32],
+) -> Result<Vec<u32>, String> {
+    let mut fixed: Vec<u32> = vec![];
+    let mut ranges: Vec<(u32, u32)> = vec![];
+    for u in block.split(',') {
+        let unit = parse_unit(u, min, max)?;
+        match u.find('~') {
+            Some(index) => ranges.push(parse_random_range(
+                &u[..index],
+                &u[(index + 1)..],
+                min,
+                max,
+            )?),
+            None => fixed.extend(unit),
+        }
+    }
+    let values = uniq_and_sort(values);
+    let chosen: Vec<u32> = values
+        .iter()
+        .filter(|v| !fixed.contains(v))
+        .cloned()
+        .collect();
+    let in_range = |v: &u32, (from, to): &(u32, u32)| from <= v && v <= to;
+    if !fixed.iter().all(|v| values.contains(v))
+        || chosen.len() > ranges.len()
+        || !chosen.iter().all(|v| ranges.iter().any(|r| in_range(v, r)))
+        || !ranges.iter().all(|r| values.iter().any(|v| in_range(v, r)))
+    {
+        return Err(format!("'{}' cannot choose {:?}", block, values));
+    }
+    Ok(values)
+}
+
 /// Returns numbers parsed from unit expression
 ///
 /// # Examples
@@ -364,6 +792,14 @@ pub fn parse_block(minute: &str, min: u32, max: u32) -> Result<Vec<u32>, String>
 /// assert_eq!(v2, vec![0, 10, 20]);
 /// ```
 ///
+/// Random value chosen by the seed `0`, see `parse_unit_with_seed`
+/// ```
+/// use cron_gate::expression;
+///
+/// let v = expression::parse_unit("0~30", 0, 59).unwrap();
+/// assert_eq!(v, expression::parse_unit_with_seed("0~30", 0, 59, 0).unwrap());
+/// ```
+///
 /// Error case
 /// ```should_panic
 /// use cron_gate::expression;
@@ -371,70 +807,99 @@ pub fn parse_block(minute: &str, min: u32, max: u32) -> Result<Vec<u32>, String>
 /// expression::parse_unit("a", 0, 3).unwrap();
 /// ```
 pub fn parse_unit(unit: &str, min: u32, max: u32) -> Result<Vec<u32>, String> {
+    parse_unit_with_seed(unit, min, max, 0)
+}
+
+/// Returns numbers parsed from unit expression with `~` chosen by the seed
+///
+/// `a~b` is a value in `a` to `b` as in OpenBSD cron, and an omitted side is
+/// the limit of the field. The value depends only on the seed, the unit and
+/// the limits, so it is the same every time.
+///
+/// # Examples
+///
+/// ```
+/// use cron_gate::expression;
+///
+/// let v = expression::parse_unit_with_seed("10~20", 0, 59, 42).unwrap();
+/// assert!(10 <= v[0] && v[0] <= 20);
+/// assert_eq!(v, expression::parse_unit_with_seed("10~20", 0, 59, 42).unwrap());
+/// assert_eq!(expression::parse_unit_with_seed("5~5", 0, 59, 42).unwrap(), vec![5]);
+///
+/// let v = expression::parse_unit_with_seed("~", 0, 3, 42).unwrap();
+/// assert!(v[0] <= 3);
+/// ```
+pub fn parse_unit_with_seed(unit: &str, min: u32, max: u32, seed: u64) -> Result<Vec<u32>, String> {
+    let (body, interval) = parse_interval(unit)?;
     let mut ret: Vec<u32> = Vec::new();
 
-    if unit.starts_with("*") {
+    if let Some(index) = body.find('~') {
+        if interval != 1 {
+            return Err(format!("Interval cannot be used with '~': '{}'", unit));
+        }
+        let (from, to) = parse_random_range(&body[..index], &body[(index + 1)..], min, max)?;
+        ret.push(from + (jitter(unit, min, max, seed) % u64::from(to - from + 1)) as u32);
+    } else if body == "*" {
         for i in min..(max + 1) {
             ret.push(i);
         }
+    } else if let Some(index) = body.find('-') {
+        ret.append(&mut parse_range(
+            &body[..index],
+            &body[(index + 1)..],
+            min,
+            max,
+        )?);
     } else {
-        let re = Regex::new(r"^(\d*)-(\d*)(/\d*)?$").unwrap();
-        match re.captures(unit) {
-            Some(caps) => {
-                ret.append(&mut parse_range(caps, min, max)?);
-            }
-            None => {
-                let n = unit
-                    .parse::<u32>()
-                    .map_err(|e| format!("Cannot parse '{}': {}", unit, e))?;
-                if n < min || max < n {
-                    return Err(format!(
-                        "Invalid range '{}': should be in {} to {}",
-                        unit, min, max
-                    ));
-                }
-                ret.push(n);
-            }
+        if interval != 1 {
+            return Err(format!("Interval needs a range or '*': '{}'", unit));
+        }
+        let n = parse_number(body)?;
+        if n < min || max < n {
+            return Err(format!(
+                "Invalid range '{}': should be in {} to {}",
+                unit, min, max
+            ));
         }
+        ret.push(n);
     }
 
-    Ok(filter_interval(&uniq_and_sort(&ret), parse_interval(unit)))
+    Ok(filter_interval(&ret, interval))
 }
 
-fn filter_interval(vec: &Vec<u32>, interval: u32) -> Vec<u32> {
+fn filter_interval(vec: &[u32], interval: u32) -> Vec<u32> {
     let mut ret = Vec::new();
-    let from: u32;
-    if vec.len() > 0 {
-        from = vec[0];
-    } else {
-        from = 0;
-    }
+    let from = vec.first().cloned().unwrap_or(0);
     for i in vec {
-        if (i - from) % interval == 0 {
+        if (i - from).is_multiple_of(interval) {
             ret.push(*i);
         }
     }
     ret
 }
 
-fn parse_interval(unit: &str) -> u32 {
-    let re = Regex::new(r".*/(\d*)$").unwrap();
-    re.captures(unit)
-        .map_or(1, |caps| caps[1].parse::<u32>().unwrap())
+/// Returns the unit without its interval and the interval (1 if absent)
+fn parse_interval(unit: &str) -> Result<(&str, u32), String> {
+    match unit.find('/') {
+        Some(index) => {
+            let interval = parse_number(&unit[(index + 1)..])?;
+            if interval == 0 {
+                return Err(format!("Interval cannot be zero: '{}'", unit));
+            }
+            Ok((&unit[..index], interval))
+        }
+        None => Ok((unit, 1)),
+    }
 }
 
-fn parse_range(caps: Captures, min: u32, max: u32) -> Result<Vec<u32>, String> {
-    let ranmge_min = caps[1]
-        .parse::<u32>()
-        .map_err(|e| format!("Cannot parse '{}': {}", &caps[1], e))?;
-    let ranmge_max = caps[2]
-        .parse::<u32>()
-        .map_err(|e| format!("Cannot parse '{}': {}", &caps[2], e))?;
+fn parse_range(left: &str, right: &str, min: u32, max: u32) -> Result<Vec<u32>, String> {
+    let ranmge_min = parse_number(left)?;
+    let ranmge_max = parse_number(right)?;
 
     if ranmge_min > ranmge_max {
         return Err(format!(
-            "Left side cannot be greater than right one: {}",
-            &caps[0]
+            "Left side cannot be greater than right one: {}-{}",
+            left, right
         ));
     }
 
@@ -453,12 +918,67 @@ fn parse_range(caps: Captures, min: u32, max: u32) -> Result<Vec<u32>, String> {
     Ok(ret)
 }
 
+/// Returns the range of `~` whose omitted sides are the limits
+pub(crate) fn parse_random_range(
+    left: &str,
+    right: &str,
+    min: u32,
+    max: u32,
+) -> Result<(u32, u32), String> {
+    let from = if left.is_empty() {
+        min
+    } else {
+        parse_number(left)?
+    };
+    let to = if right.is_empty() {
+        max
+    } else {
+        parse_number(right)?
+    };
+    if from < min || max < to {
+        return Err(format!(
+            "Invalid range '{}~{}': should be in {} to {}",
+            left, right, min, max
+        ));
+    }
+    if from > to {
+        return Err(format!(
+            "Left side cannot be greater than right one: {}~{}",
+            left, right
+        ));
+    }
+    Ok((from, to))
+}
+
+/// Returns a hash of the seed, the unit and the limits of the field
+///
+/// FNV-1a finalized by SplitMix64, so that units differing slightly
+/// or fields having the same unit get unrelated values.
+fn jitter(unit: &str, min: u32, max: u32, seed: u64) -> u64 {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for b in format!("{} {}-{}", unit, min, max).bytes() {
+        h = (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3);
+    }
+    h = (h ^ seed).wrapping_add(0x9e37_79b9_7f4a_7c15);
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^ (h >> 31)
+}
+
+/// Parses digits only, rejecting signs and spaces which `str::parse` may accept
+fn parse_number(text: &str) -> Result<u32, String> {
+    if text.is_empty() || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("Cannot parse '{}': not a number", text));
+    }
+    text.parse::<u32>()
+        .map_err(|e| format!("Cannot parse '{}': {}", text, e))
+}
+
 /// Returns Vec<u32> having unique and sorted values
-fn uniq_and_sort(v: &Vec<u32>) -> Vec<u32> {
-    let set: HashSet<_> = v.clone().drain(..).collect();
-    let mut vec = vec![];
-    vec.extend(set.into_iter());
+fn uniq_and_sort(v: &[u32]) -> Vec<u32> {
+    let mut vec = v.to_vec();
     vec.sort_unstable();
+    vec.dedup();
     vec
 }
 
@@ -555,78 +1075,212 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_earler_excuting_datetimes_sparse() {
+        let e = Expression::new("0 0 29 2 1 command").unwrap();
+        let from = Local
+            .datetime_from_str("2019/1/1 0:0", DATE_FORMAT)
+            .unwrap();
+        assert_eq!(
+            e.earler_excuting_datetimes(from, 2),
+            [
+                Local
+                    .datetime_from_str("2044/2/29 0:0", DATE_FORMAT)
+                    .unwrap(),
+                Local
+                    .datetime_from_str("2072/2/29 0:0", DATE_FORMAT)
+                    .unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_earler_excuting_datetimes_carry() {
+        let e = Expression::new("0 0 31 * * command").unwrap();
+        let from = Local
+            .datetime_from_str("2019/12/30 23:58", DATE_FORMAT)
+            .unwrap();
+        assert_eq!(
+            e.earler_excuting_datetimes(from, 2),
+            [
+                Local
+                    .datetime_from_str("2019/12/31 0:0", DATE_FORMAT)
+                    .unwrap(),
+                Local
+                    .datetime_from_str("2020/1/31 0:0", DATE_FORMAT)
+                    .unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_earler_excuting_datetimes_same_as_scan() {
+        let from = Local
+            .datetime_from_str("2019/12/1 0:0", DATE_FORMAT)
+            .unwrap();
+        for text in &[
+            "* * * * * command",
+            "*/7 */5 * * * command",
+            "0 1-20/3 28 5 2 command",
+            "0 0 31 * * command",
+            "30 9 * * 1-5 command",
+            "0 12 1,15 */2 0,7 command",
+            "59 23 31 12 * command",
+        ] {
+            let e = Expression::new(text).unwrap();
+            assert_eq!(
+                e.earler_excuting_datetimes(from, 20),
+                e.earler_excuting_datetimes_by_scan(from, 20),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_days_in_month() {
+        assert_eq!(days_in_month(2019, 2), 28);
+        assert_eq!(days_in_month(2020, 2), 29);
+        assert_eq!(days_in_month(2100, 2), 28);
+        assert_eq!(days_in_month(2000, 2), 29);
+        assert_eq!(days_in_month(2019, 4), 30);
+    }
+
+    #[test]
+    fn test_earler_excuting_datetimes_never_fires() {
+        let e = Expression::new("0 0 30 2 * command").unwrap();
+        let from = Local
+            .datetime_from_str("2019/1/1 0:0", DATE_FORMAT)
+            .unwrap();
+        assert!(e.earler_excuting_datetimes(from, 3).is_empty());
+    }
+
+    #[test]
+    fn test_never_fires_reason() {
+        let e = Expression::new("0 0 31 4,6,9,11 * command").unwrap();
+        assert_eq!(
+            e.never_fires_reason().unwrap(),
+            "Never fires: date '31' does not exist in April (30 days at most), June (30 days at most), September (30 days at most), November (30 days at most)"
+        );
+        let mut e = Expression::new("0 0 * * * command").unwrap();
+        assert_eq!(e.never_fires_reason(), None);
+        e.hour_vec = vec![];
+        assert_eq!(
+            e.never_fires_reason().unwrap(),
+            "Never fires: hour '0' matches no value"
+        );
+    }
+
+    #[test]
+    fn test_max_days_in_month() {
+        assert_eq!(max_days_in_month(1), 31);
+        assert_eq!(max_days_in_month(2), 29);
+        assert_eq!(max_days_in_month(4), 30);
+        assert_eq!(max_days_in_month(12), 31);
+    }
+
     #[test]
     fn test_is_on_weekday() {
         let tue = Local
             .datetime_from_str("2019/5/28 0:0", DATE_FORMAT)
             .unwrap();
-        assert!(is_on_weekday(&tue.weekday(), &vec![2]));
-        assert!(!is_on_weekday(&tue.weekday(), &vec![0, 1, 3, 4, 5, 6, 7]));
+        assert!(is_on_weekday(&tue.weekday(), &[2]));
+        assert!(!is_on_weekday(&tue.weekday(), &[0, 1, 3, 4, 5, 6, 7]));
 
         let sun = Local
             .datetime_from_str("2019/5/26 0:0", DATE_FORMAT)
             .unwrap();
-        assert!(is_on_weekday(&sun.weekday(), &vec![0]));
-        assert!(is_on_weekday(&sun.weekday(), &vec![7]));
-        assert!(!is_on_weekday(&sun.weekday(), &vec![1, 2, 3, 4, 5, 6]));
+        assert!(is_on_weekday(&sun.weekday(), &[0]));
+        assert!(is_on_weekday(&sun.weekday(), &[7]));
+        assert!(!is_on_weekday(&sun.weekday(), &[1, 2, 3, 4, 5, 6]));
     }
 
     #[test]
     fn test_filter_interval() {
-        assert_eq!(filter_interval(&vec![0, 1, 2, 3, 4], 3), [0, 3]);
-        assert_eq!(filter_interval(&vec![3, 4, 5, 6, 7], 2), [3, 5, 7]);
+        assert_eq!(filter_interval(&[0, 1, 2, 3, 4], 3), [0, 3]);
+        assert_eq!(filter_interval(&[3, 4, 5, 6, 7], 2), [3, 5, 7]);
     }
 
     #[test]
     fn test_parse_interval() {
-        assert_eq!(parse_interval("3"), 1);
-        assert_eq!(parse_interval("1/2"), 2);
-        assert_eq!(parse_interval("1/10"), 10);
+        assert_eq!(parse_interval("3").unwrap(), ("3", 1));
+        assert_eq!(parse_interval("1/2").unwrap(), ("1", 2));
+        assert_eq!(parse_interval("*/10").unwrap(), ("*", 10));
+        assert!(parse_interval("1/").is_err());
+        assert!(parse_interval("1/0").is_err());
+        assert!(parse_interval("1/a").is_err());
     }
 
     #[test]
     fn test_parse_unit() {
-        match parse_unit("0", 1, 4) {
-            Ok(_) => assert!(false),
-            Err(_) => assert!(true),
-        }
-        match parse_unit("1", 1, 4) {
-            Ok(n) => assert_eq!(n, [1]),
-            Err(_) => assert!(false),
-        }
-        match parse_unit("4", 1, 4) {
-            Ok(n) => assert_eq!(n, [4]),
-            Err(_) => assert!(false),
-        }
-        match parse_unit("5", 1, 4) {
-            Ok(_) => assert!(false),
-            Err(_) => assert!(true),
-        }
+        assert!(parse_unit("0", 1, 4).is_err());
+        assert_eq!(parse_unit("1", 1, 4).unwrap(), [1]);
+        assert_eq!(parse_unit("4", 1, 4).unwrap(), [4]);
+        assert!(parse_unit("5", 1, 4).is_err());
+        assert!(parse_unit("*a", 1, 4).is_err());
+        assert!(parse_unit("2/2", 1, 4).is_err());
+        assert!(parse_unit("1-", 1, 4).is_err());
+        assert!(parse_unit("+1", 1, 4).is_err());
+        assert_eq!(parse_unit("*/2", 1, 4).unwrap(), [1, 3]);
+    }
+
+    #[test]
+    fn test_parse_unit_with_seed() {
+        let chosen: Vec<u32> = (0..100)
+            .map(|seed| parse_unit_with_seed("10~20", 0, 59, seed).unwrap()[0])
+            .collect();
+        assert!(chosen.iter().all(|v| 10 <= *v && *v <= 20));
+        assert!(chosen.iter().any(|v| *v != chosen[0]));
+        assert!(parse_unit_with_seed("40~", 0, 59, 1).unwrap()[0] >= 40);
+        assert!(parse_unit_with_seed("~5", 0, 59, 1).unwrap()[0] <= 5);
+
+        assert!(parse_unit_with_seed("20~10", 0, 59, 1).is_err());
+        assert!(parse_unit_with_seed("0~60", 0, 59, 1).is_err());
+        assert!(parse_unit_with_seed("0~30/5", 0, 59, 1).is_err());
+        assert!(parse_unit_with_seed("a~3", 0, 59, 1).is_err());
+        assert!(parse_unit_with_seed("1~2~3", 0, 59, 1).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_parse_block_with_chosen() {
+        assert_eq!(parse_block_with_chosen("0~30", 0, 59, &[17]).unwrap(), [17]);
+        assert_eq!(
+            parse_block_with_chosen("5,40~,0~30", 0, 59, &[45, 5, 17]).unwrap(),
+            [5, 17, 45]
+        );
+        // A value chosen twice, or the same as a fixed one
+        assert_eq!(
+            parse_block_with_chosen("0~9,5~9", 0, 59, &[7]).unwrap(),
+            [7]
+        );
+        assert_eq!(parse_block_with_chosen("5,0~9", 0, 59, &[5]).unwrap(), [5]);
+
+        assert!(parse_block_with_chosen("0~30", 0, 59, &[31]).is_err());
+        assert!(parse_block_with_chosen("0~30", 0, 59, &[1, 2]).is_err());
+        assert!(parse_block_with_chosen("5,0~30", 0, 59, &[17]).is_err());
+        assert!(parse_block_with_chosen("0~9,20~29", 0, 59, &[1, 2]).is_err());
+        assert!(parse_block_with_chosen("0~60", 0, 59, &[1]).is_err());
+    }
+
+    #[test]
+    fn test_jitter() {
+        assert_eq!(jitter("0~30", 0, 59, 1), jitter("0~30", 0, 59, 1));
+        assert_ne!(jitter("0~30", 0, 59, 1), jitter("0~30", 0, 59, 2));
+        assert_ne!(jitter("0~30", 0, 59, 1), jitter("0~30", 0, 23, 1));
     }
 
     #[test]
     fn test_parse_range() {
-        let re = Regex::new(r"^(\d)-(\d)$").unwrap();
-        match parse_range(re.captures("1-3").unwrap(), 1, 3) {
-            Ok(v) => assert_eq!(v, [1, 2, 3]),
-            Err(_) => assert!(false),
-        }
-        match parse_range(re.captures("1-4").unwrap(), 1, 3) {
-            Ok(_) => assert!(false),
-            Err(_) => assert!(true),
-        }
-        match parse_range(re.captures("0-3").unwrap(), 1, 3) {
-            Ok(_) => assert!(false),
-            Err(_) => assert!(true),
-        }
-        match parse_range(re.captures("3-1").unwrap(), 1, 3) {
-            Ok(_) => assert!(false),
-            Err(_) => assert!(true),
-        }
+        assert_eq!(parse_range("1", "3", 1, 3).unwrap(), [1, 2, 3]);
+        assert!(parse_range("1", "4", 1, 3).is_err());
+        assert!(parse_range("0", "3", 1, 3).is_err());
+        assert!(parse_range("3", "1", 1, 3).is_err());
+        assert!(parse_range("", "1", 1, 3).is_err());
     }
 
     #[test]
     fn test_uniq_and_sort() {
-        assert_eq!(uniq_and_sort(&vec![1, 1, 2, 2, 3]), vec![1, 2, 3]);
+        assert_eq!(uniq_and_sort(&[1, 1, 2, 2, 3]), vec![1, 2, 3]);
     }
 }
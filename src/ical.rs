@@ -0,0 +1,258 @@
+//! iCalendar (RFC 5545) export
+//!
+//! Occurrences are exported either as explicit VEVENTs in UTC,
+//! or as a single VEVENT with an `RRULE`. A recurring event has floating
+//! local times, so calendars show it at the same wall-clock time as cron does.
+
+use crate::expression::{CronLine, Expression};
+use crate::rrule::to_rrule;
+use chrono::{DateTime, Duration, Local, NaiveDateTime, Utc};
+
+const PRODID: &str = "-//cron_gate//cron_gate//EN";
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Duration of each event
+    pub duration: Duration,
+    /// Export a single VEVENT with an RRULE instead of one VEVENT per occurrence
+    pub recurring: bool,
+    /// DTSTAMP of the events, now by default: give the time of a `Clock`
+    /// for reproducible output
+    pub stamp: DateTime<Utc>,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            duration: Duration::minutes(15),
+            recurring: false,
+            stamp: Utc::now(),
+        }
+    }
+}
+
+/// Returns a VCALENDAR having the expressions
+///
+/// Each expression becomes its next `number` occurrences after `after`,
+/// or a recurring event if `options.recurring` is set.
+/// Expressions which never fire have no event.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::offset::TimeZone;
+/// use chrono::{Local, Utc};
+/// use cron_gate::expression::Expression;
+/// use cron_gate::ical::{export, Options};
+///
+/// let after = Local.datetime_from_str("2019/5/28 0:0", "%Y/%m/%d %H:%M").unwrap();
+/// let options = Options { stamp: Utc.ymd(2019, 5, 1).and_hms(0, 0, 0), ..Options::default() };
+/// let e = Expression::new("0 9 * * 1-5 backup.sh").unwrap();
+///
+/// let ics = export(&[e.clone()], after, 3, &options);
+/// assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+/// assert_eq!(ics.matches("BEGIN:VEVENT").count(), 3);
+/// assert!(ics.contains("SUMMARY:backup.sh\r\n"));
+///
+/// let ics = export(&[e], after, 3, &Options { recurring: true, ..options });
+/// assert_eq!(ics.matches("BEGIN:VEVENT").count(), 1);
+/// assert!(ics.contains("DTSTART:20190528T090000\r\n"));
+/// assert!(ics.contains("RRULE:FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR;BYHOUR=9;BYMINUTE=0\r\n"));
+/// ```
+pub fn export(
+    expressions: &[Expression],
+    after: DateTime<Local>,
+    number: usize,
+    options: &Options,
+) -> String {
+    let mut events = vec![];
+    for expression in expressions {
+        if options.recurring {
+            events.extend(recurring_event(expression, after, options));
+        } else {
+            events.extend(events_of(
+                &expression.executing_dates(after, number),
+                expression,
+                options,
+            ));
+        }
+    }
+    calendar(&events)
+}
+
+/// Returns a VCALENDAR wrapping the VEVENTs
+pub fn calendar(events: &[String]) -> String {
+    let mut ret = String::new();
+    push_line(&mut ret, "BEGIN:VCALENDAR");
+    push_line(&mut ret, "VERSION:2.0");
+    push_line(&mut ret, &format!("PRODID:{}", PRODID));
+    push_line(&mut ret, "CALSCALE:GREGORIAN");
+    for event in events {
+        ret.push_str(event);
+    }
+    push_line(&mut ret, "END:VCALENDAR");
+    ret
+}
+
+/// Returns a VEVENT per line, starting in UTC
+pub fn events_of(lines: &[CronLine], expression: &Expression, options: &Options) -> Vec<String> {
+    let id = fnv1a(&expression.to_string());
+    lines
+        .iter()
+        .map(|line| {
+            let start = line.datetime.with_timezone(&Utc);
+            let end = start + options.duration;
+            event(
+                &format!("{:016x}-{}@cron_gate", id, format_utc(&start)),
+                &format!("DTSTART:{}", format_utc(&start)),
+                &format!("DTEND:{}", format_utc(&end)),
+                None,
+                expression,
+                options,
+            )
+        })
+        .collect()
+}
+
+/// Returns a VEVENT recurring by the RRULE of the expression, see `rrule::to_rrule`
+///
+/// It starts at the first occurrence after `after`,
+/// or is None if the expression never fires.
+pub fn recurring_event(
+    expression: &Expression,
+    after: DateTime<Local>,
+    options: &Options,
+) -> Option<String> {
+    let rule = to_rrule(expression).ok()?;
+    let first = expression.earler_excuting_datetimes(after, 1).pop()?;
+    let start = first.naive_local();
+    Some(event(
+        &format!("{:016x}@cron_gate", fnv1a(&expression.to_string())),
+        &format!("DTSTART:{}", format_floating(&start)),
+        &format!("DTEND:{}", format_floating(&(start + options.duration))),
+        Some(&rule),
+        expression,
+        options,
+    ))
+}
+
+fn event(
+    uid: &str,
+    dtstart: &str,
+    dtend: &str,
+    rrule: Option<&str>,
+    expression: &Expression,
+    options: &Options,
+) -> String {
+    let mut ret = String::new();
+    push_line(&mut ret, "BEGIN:VEVENT");
+    push_line(&mut ret, &format!("UID:{}", uid));
+    push_line(&mut ret, &format!("DTSTAMP:{}", format_utc(&options.stamp)));
+    push_line(&mut ret, dtstart);
+    push_line(&mut ret, dtend);
+    if let Some(rule) = rrule {
+        push_line(&mut ret, &format!("RRULE:{}", rule));
+    }
+    push_line(
+        &mut ret,
+        &format!("SUMMARY:{}", escape_text(&expression.command)),
+    );
+    push_line(
+        &mut ret,
+        &format!("DESCRIPTION:{}", escape_text(&expression.schedule())),
+    );
+    push_line(&mut ret, "END:VEVENT");
+    ret
+}
+
+fn format_utc(datetime: &DateTime<Utc>) -> String {
+    datetime.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn format_floating(datetime: &NaiveDateTime) -> String {
+    datetime.format("%Y%m%dT%H%M%S").to_string()
+}
+
+/// Escapes TEXT values
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+        .replace('\r', "")
+}
+
+/// Pushes the content line folded at 75 octets with CRLF
+fn push_line(ret: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            ret.push_str("\r\n ");
+            width = 1;
+        }
+        ret.push(c);
+        width += c.len_utf8();
+    }
+    ret.push_str("\r\n");
+}
+
+/// Stable hash for UIDs
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::DATE_FORMAT;
+    use chrono::offset::TimeZone;
+
+    #[test]
+    fn test_events_of() {
+        let e = Expression::new("0 9 * * * a, b; c").unwrap();
+        let from = Local
+            .datetime_from_str("2019/5/28 0:0", DATE_FORMAT)
+            .unwrap();
+        let options = Options {
+            duration: Duration::hours(1),
+            ..Options::default()
+        };
+        let events = events_of(&e.executing_dates(from, 2), &e, &options);
+        assert_eq!(events.len(), 2);
+        assert_ne!(events[0], events[1]);
+
+        let start = Local
+            .datetime_from_str("2019/5/28 9:0", DATE_FORMAT)
+            .unwrap();
+        let end = start + Duration::hours(1);
+        assert!(events[0].contains(&format!(
+            "DTSTART:{}\r\n",
+            format_utc(&start.with_timezone(&Utc))
+        )));
+        assert!(events[0].contains(&format!(
+            "DTEND:{}\r\n",
+            format_utc(&end.with_timezone(&Utc))
+        )));
+        assert!(events[0].contains("SUMMARY:a\\, b\\; c\r\n"));
+        assert!(events[0].contains("DESCRIPTION:0 9 * * *\r\n"));
+    }
+
+    #[test]
+    fn test_recurring_event_never_fires() {
+        let e = Expression::new("0 0 31 4 *").unwrap();
+        assert_eq!(recurring_event(&e, Local::now(), &Options::default()), None);
+    }
+
+    #[test]
+    fn test_push_line() {
+        let mut ret = String::new();
+        push_line(&mut ret, &"a".repeat(80));
+        assert_eq!(ret, format!("{}\r\n {}\r\n", "a".repeat(75), "a".repeat(5)));
+
+        let mut ret = String::new();
+        push_line(&mut ret, &format!("{}あ", "a".repeat(74)));
+        assert_eq!(ret, format!("{}\r\n あ\r\n", "a".repeat(74)));
+    }
+}
@@ -0,0 +1,251 @@
+//! Jenkins `H` syntax
+//!
+//! `H` is resolved as Jenkins does: values are drawn from `java.util.Random`
+//! seeded by the MD5 of the seed, which is the full name of the job in Jenkins,
+//! in order of appearance in the spec.
+
+const LOWER_BOUNDS: [u32; 5] = [0, 0, 1, 1, 0];
+const UPPER_BOUNDS: [u32; 5] = [59, 23, 31, 12, 7];
+const FIELD_NAMES: [&str; 5] = ["minute", "hour", "day-of-month", "month", "day-of-week"];
+
+/// Returns the spec having `H` resolved by the seed
+///
+/// Aliases like `@daily` are expanded into `H` as Jenkins does.
+///
+/// # Examples
+///
+/// ```
+/// use cron_gate::jenkins::resolve;
+///
+/// assert_eq!(resolve("H H(0-7) * * *", "my-job").unwrap(), resolve("H H(0-7) * * *", "my-job").unwrap());
+/// assert_eq!(resolve("@hourly", "my-job").unwrap(), resolve("H * * * *", "my-job").unwrap());
+/// assert_eq!(resolve("*/15 9-17 * * 1-5", "my-job").unwrap(), "*/15 9-17 * * 1-5");
+/// assert!(resolve("H(0-99) * * * *", "my-job").is_err());
+/// ```
+pub fn resolve(spec: &str, seed: &str) -> Result<String, String> {
+    let spec = spec.trim();
+    let text = match spec {
+        "@yearly" | "@annually" => "H H H H *",
+        "@monthly" => "H H H * *",
+        "@weekly" => "H H * * H",
+        "@daily" => "H H * * *",
+        "@midnight" => "H H(0-2) * * *",
+        "@hourly" => "H * * * *",
+        s if s.starts_with('@') => return Err(format!("Unknown alias '{}'", s)),
+        s => s,
+    };
+
+    let fields: Vec<&str> = text.split_whitespace().collect();
+    if fields.len() != 5 {
+        return Err(format!(
+            "'{}' should have 5 fields in Jenkins, but has {}",
+            spec,
+            fields.len()
+        ));
+    }
+
+    let mut hash = Hash::from(seed);
+    let mut resolved = vec![];
+    for (field, text) in fields.iter().enumerate() {
+        let terms: Result<Vec<String>, String> = text
+            .split(',')
+            .map(|term| resolve_term(term, field, &mut hash))
+            .collect();
+        resolved.push(terms?.join(","));
+    }
+    Ok(resolved.join(" "))
+}
+
+/// Resolves `H`, `H(a-b)` and `H/step` and leaves other terms as they are
+fn resolve_term(term: &str, field: usize, hash: &mut Hash) -> Result<String, String> {
+    let rest = match term.strip_prefix('H') {
+        Some(rest) => rest,
+        None => return Ok(term.to_string()),
+    };
+    let error =
+        |message: String| format!("Error on {}: '{}'\n{}", FIELD_NAMES[field], term, message);
+
+    let (start, end, rest) = match rest.strip_prefix('(') {
+        Some(range) => {
+            let close = range
+                .find(')')
+                .ok_or_else(|| error("')' is missing".to_string()))?;
+            let (from, to) = match range[..close].find('-') {
+                Some(index) => (&range[..index], &range[(index + 1)..close]),
+                None => return Err(error("H(a-b) needs a range".to_string())),
+            };
+            let bound = |v: &str| {
+                v.parse::<u32>()
+                    .ok()
+                    .filter(|v| LOWER_BOUNDS[field] <= *v && *v <= UPPER_BOUNDS[field])
+                    .ok_or_else(|| {
+                        error(format!(
+                            "'{}' should be in {} to {}",
+                            v, LOWER_BOUNDS[field], UPPER_BOUNDS[field]
+                        ))
+                    })
+            };
+            let (start, end) = (bound(from)?, bound(to)?);
+            if start > end {
+                return Err(error(format!("Invalid range '{}-{}'", start, end)));
+            }
+            (start, end, &range[(close + 1)..])
+        }
+        None => {
+            // Jenkins keeps H in days which every month has, and Sunday only as 0
+            let end = match field {
+                2 => 28,
+                4 => 6,
+                _ => UPPER_BOUNDS[field],
+            };
+            (LOWER_BOUNDS[field], end, rest)
+        }
+    };
+
+    let step = match rest.strip_prefix('/') {
+        Some(step) => step
+            .parse::<u32>()
+            .map_err(|_| error(format!("Invalid step '{}'", step)))?,
+        None if rest.is_empty() => 1,
+        None => return Err(error(format!("Unexpected '{}'", rest))),
+    };
+    if step == 0 || step > end - start + 1 {
+        return Err(error(format!(
+            "Step {} should be in 1 to {}",
+            step,
+            end - start + 1
+        )));
+    }
+
+    if step == 1 {
+        return Ok((start + hash.next(end - start + 1)).to_string());
+    }
+    let values: Vec<String> = (start + hash.next(step)..=end)
+        .step_by(step as usize)
+        .map(|v| v.to_string())
+        .collect();
+    Ok(values.join(","))
+}
+
+/// `hudson.scheduler.Hash.from`
+struct Hash(JavaRandom);
+
+impl Hash {
+    fn from(seed: &str) -> Hash {
+        let mut digest = md5::compute(seed.as_bytes()).0;
+        for i in 8..16 {
+            digest[i % 8] ^= digest[i];
+        }
+        let seed = digest[..8]
+            .iter()
+            .fold(0u64, |l, b| (l << 8) | u64::from(*b));
+        Hash(JavaRandom::new(seed))
+    }
+
+    fn next(&mut self, n: u32) -> u32 {
+        self.0.next_int(n as i32) as u32
+    }
+}
+
+/// `java.util.Random`
+struct JavaRandom {
+    seed: u64,
+}
+
+const MULTIPLIER: u64 = 0x5_deec_e66d;
+const MASK: u64 = (1 << 48) - 1;
+
+impl JavaRandom {
+    fn new(seed: u64) -> JavaRandom {
+        JavaRandom {
+            seed: (seed ^ MULTIPLIER) & MASK,
+        }
+    }
+
+    fn next(&mut self, bits: u32) -> i32 {
+        self.seed = (self.seed.wrapping_mul(MULTIPLIER).wrapping_add(0xb)) & MASK;
+        (self.seed >> (48 - bits)) as i32
+    }
+
+    fn next_int(&mut self, bound: i32) -> i32 {
+        let mut r = self.next(31);
+        let m = bound - 1;
+        if bound & m == 0 {
+            return ((i64::from(bound) * i64::from(r)) >> 31) as i32;
+        }
+        let mut u = r;
+        loop {
+            r = u % bound;
+            if u.wrapping_sub(r).wrapping_add(m) >= 0 {
+                return r;
+            }
+            u = self.next(31);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_java_random() {
+        // new Random(42).nextInt() and new Random(0).nextInt() in Java
+        assert_eq!(JavaRandom::new(42).next(32), -1_170_105_035);
+        assert_eq!(JavaRandom::new(0).next(32), -1_155_484_576);
+
+        let mut r = JavaRandom::new(42);
+        let values: Vec<i32> = (0..5).map(|_| r.next_int(10)).collect();
+        assert_eq!(values, vec![0, 3, 8, 4, 0]);
+    }
+
+    #[test]
+    fn test_resolve() {
+        // Values computed by hudson.scheduler.Hash with the same seeds
+        assert_eq!(resolve("H H(0-7) H H *", "job").unwrap(), "31 7 8 12 *");
+        assert_eq!(resolve("@midnight", "my-job").unwrap(), "18 2 * * *");
+        assert_eq!(
+            resolve("H/15 H * * H", "folder/nightly").unwrap(),
+            "11,26,41,56 7 * * 0"
+        );
+        assert_ne!(
+            resolve("H * * * *", "job").unwrap(),
+            resolve("H * * * *", "other").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_resolve_step() {
+        let resolved = resolve("H/15 * * * *", "job").unwrap();
+        let minutes: Vec<u32> = resolved
+            .split(' ')
+            .next()
+            .unwrap()
+            .split(',')
+            .map(|m| m.parse().unwrap())
+            .collect();
+        assert_eq!(minutes.len(), 4);
+        assert!(minutes.windows(2).all(|w| w[1] - w[0] == 15));
+        assert_eq!(
+            resolve("H(0-29)/30 * * * *", "job")
+                .unwrap()
+                .split(' ')
+                .next()
+                .unwrap()
+                .split(',')
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_resolve_error() {
+        assert!(resolve("H * * *", "job").is_err());
+        assert!(resolve("@often", "job").is_err());
+        assert!(resolve("H(5-1) * * * *", "job").is_err());
+        assert!(resolve("H(0-5 * * * *", "job").is_err());
+        assert!(resolve("H/0 * * * *", "job").is_err());
+        assert!(resolve("H/61 * * * *", "job").is_err());
+        assert!(resolve("Hx * * * *", "job").is_err());
+    }
+}
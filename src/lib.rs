@@ -1,29 +1,109 @@
 extern crate wasm_bindgen;
+pub mod calendar;
+pub mod clock;
+pub mod compact;
+pub mod crontab;
+pub mod datetime;
+pub mod describe;
+pub mod dialect;
+pub mod explain;
 pub mod expression;
+pub mod ical;
+pub mod jenkins;
+pub mod lint;
+#[cfg(feature = "yaml")]
+pub mod manifest;
+pub mod output;
+pub mod rrule;
+#[cfg(feature = "runner")]
+pub mod runner;
+pub mod scheduler;
+#[cfg(feature = "serde")]
+pub mod serialize;
+#[cfg(feature = "async")]
+pub mod stream;
+pub mod systemd;
+pub mod translation;
+pub mod validate;
+pub mod wasm;
 
-use chrono::offset::TimeZone;
-use chrono::Local;
+use clock::Clock;
+use datetime::{parse_datetime_with_clock, validate_format};
 use expression::{Expression, DATE_FORMAT};
 use wasm_bindgen::prelude::*;
 
+/// Returns lines joined by newlines, see `wasm::JsExpression` for structured data
 #[wasm_bindgen]
 pub fn get_datetimes(text: &str, after_str: &str, number: i32) -> String {
-    let after;
-    match Local.datetime_from_str(after_str, DATE_FORMAT) {
-        Ok(a) => after = a,
+    get_formatted_datetimes(text, after_str, number, DATE_FORMAT)
+}
+
+/// Returns lines having the datetime in the strftime format
+#[wasm_bindgen]
+pub fn get_formatted_datetimes(
+    text: &str,
+    after_str: &str,
+    number: i32,
+    output_format: &str,
+) -> String {
+    // `Local::now` is not implemented on wasm32
+    #[cfg(target_arch = "wasm32")]
+    let clock = wasm::JsClock;
+    #[cfg(not(target_arch = "wasm32"))]
+    let clock = clock::SystemClock;
+    formatted_datetimes(&clock, text, after_str, number, output_format)
+}
+
+fn formatted_datetimes<C: Clock + ?Sized>(
+    clock: &C,
+    text: &str,
+    after_str: &str,
+    number: i32,
+    output_format: &str,
+) -> String {
+    if let Err(e) = validate_format(output_format) {
+        return e;
+    }
+
+    let after = match parse_datetime_with_clock(after_str, clock) {
+        Ok(a) => a,
         Err(e) => {
             return format!("{} is an invalid format of 'after': {}", after_str, e);
         }
-    }
+    };
 
     match Expression::new(text) {
-        Ok(exp) => {
-            let datetimes = exp.executing_dates(after, number as usize);
-            let vec: Vec<String> = datetimes.iter().map(|d| d.to_string()).collect();
-            return vec.join("\n");
-        }
-        Err(e) => {
-            return format!("{} is an invalid format of 'cron': {}", text, e);
-        }
+        Ok(exp) => match exp.try_executing_dates(after, number as usize) {
+            Ok(datetimes) => {
+                let vec: Vec<String> = datetimes.iter().map(|d| d.format(output_format)).collect();
+                vec.join("\n")
+            }
+            Err(reason) => reason,
+        },
+        Err(e) => format!("{} is an invalid format of 'cron': {}", text, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Local, TimeZone};
+    use clock::{FixedClock, UnreadClock};
+
+    #[test]
+    fn test_formatted_datetimes() {
+        let clock = FixedClock(Local.ymd(2019, 5, 28).and_hms(9, 30, 0));
+        let datetimes =
+            |after| formatted_datetimes(&clock, "0 9,12 * * * a", after, 2, DATE_FORMAT);
+        assert_eq!(datetimes("now"), "2019/05/28 12:00 a\n2019/05/29 09:00 a");
+        assert_eq!(datetimes("+3h"), "2019/05/29 09:00 a\n2019/05/29 12:00 a");
+    }
+
+    #[test]
+    fn test_formatted_datetimes_absolute() {
+        let datetimes =
+            |after| formatted_datetimes(&UnreadClock, "0 9,12 * * * a", after, 1, DATE_FORMAT);
+        assert_eq!(datetimes("2019/05/28 09:30"), "2019/05/28 12:00 a");
+        assert_eq!(datetimes("2019-05-29"), "2019/05/29 09:00 a");
     }
 }
@@ -0,0 +1,138 @@
+//! Warnings about valid but suspicious expressions
+
+use crate::expression::{max_days_in_month, Expression, MONTH_NAMES};
+
+/// Returns warnings about the expression, empty if nothing is suspicious
+///
+/// # Examples
+///
+/// ```
+/// use cron_gate::expression::Expression;
+/// use cron_gate::lint::lint;
+///
+/// assert!(lint(&Expression::new("0 9 * * 1-5 command").unwrap()).is_empty());
+/// assert_eq!(
+///     lint(&Expression::new("* 9 * * * command").unwrap()),
+///     ["Minute '*' runs every minute during hour '9': did you mean '0 9'?"]
+/// );
+/// ```
+pub fn lint(expression: &Expression) -> Vec<String> {
+    if let Some(reason) = expression.never_fires_reason() {
+        return vec![reason];
+    }
+
+    let mut warnings = vec![];
+    if expression.minute == "*" && expression.hour_vec.len() < 24 {
+        warnings.push(format!(
+            "Minute '*' runs every minute during hour '{}': did you mean '0 {}'?",
+            expression.hour, expression.hour
+        ));
+    }
+    for (name, raw, values, cycle) in [
+        ("minute", &expression.minute, &expression.minute_vec, 60),
+        ("hour", &expression.hour, &expression.hour_vec, 24),
+    ]
+    .iter()
+    {
+        if let Some(warning) = uneven_step(name, raw, values, *cycle) {
+            warnings.push(warning);
+        }
+    }
+    // Vixie cron decides by whether the field starts with '*', not by its values
+    if !expression.date.starts_with('*') && !expression.day.starts_with('*') {
+        warnings.push(format!(
+            "Both date '{}' and day '{}' are restricted: they must both match here, \
+             but Vixie cron and POSIX run when either matches",
+            expression.date, expression.day
+        ));
+    }
+    if let Some(warning) = skipped_months(expression) {
+        warnings.push(warning);
+    }
+    warnings
+}
+
+/// Warns a step like `*/7` leaving a shorter gap at the end of the cycle
+fn uneven_step(name: &str, raw: &str, values: &[u32], cycle: u32) -> Option<String> {
+    let step: u32 = raw.strip_prefix("*/")?.parse().ok()?;
+    if cycle.is_multiple_of(step) || values.len() < 2 {
+        return None;
+    }
+    let last = values[values.len() - 1];
+    Some(format!(
+        "Step '{}' on {} does not divide {}: the gap from {} to {} is {}, not {}",
+        raw,
+        name,
+        cycle,
+        last,
+        values[0],
+        values[0] + cycle - last,
+        step
+    ))
+}
+
+/// Warns months skipped because they have none of the dates
+fn skipped_months(expression: &Expression) -> Option<String> {
+    let smallest_date = expression.date_vec[0];
+    let skipped: Vec<&str> = expression
+        .month_vec
+        .iter()
+        .filter(|m| smallest_date > max_days_in_month(**m))
+        .map(|m| MONTH_NAMES[(*m - 1) as usize])
+        .collect();
+    if !skipped.is_empty() {
+        return Some(format!(
+            "Date '{}' skips {}",
+            expression.date,
+            skipped.join(", ")
+        ));
+    }
+    if smallest_date == 29 && expression.month_vec == [2] {
+        return Some(format!(
+            "Date '{}' in February runs only in leap years",
+            expression.date
+        ));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lint_str(text: &str) -> Vec<String> {
+        lint(&Expression::new(text).unwrap())
+    }
+
+    #[test]
+    fn test_lint() {
+        assert!(lint_str("*/15 * * * *").is_empty());
+        assert!(lint_str("* * * * *").is_empty());
+        assert_eq!(
+            lint_str("0 0 30 2 *"),
+            ["Never fires: date '30' does not exist in February (29 days at most)"]
+        );
+        assert_eq!(
+            lint_str("*/7 * * * *"),
+            ["Step '*/7' on minute does not divide 60: the gap from 56 to 0 is 4, not 7"]
+        );
+        assert_eq!(lint_str("0 0 13 * 5").len(), 1);
+        assert_eq!(lint_str("0 0 1-31 * 5").len(), 1);
+        assert!(lint_str("0 0 */2 * 5").is_empty());
+        assert_eq!(
+            lint_str("0 0 31 * *"),
+            ["Date '31' skips February, April, June, September, November"]
+        );
+        assert_eq!(
+            lint_str("0 0 29 2 *"),
+            ["Date '29' in February runs only in leap years"]
+        );
+    }
+
+    #[test]
+    fn test_uneven_step() {
+        assert_eq!(uneven_step("hour", "*/6", &[0, 6, 12, 18], 24), None);
+        assert!(uneven_step("hour", "*/5", &[0, 5, 10, 15, 20], 24).is_some());
+        assert_eq!(uneven_step("hour", "0-10/3", &[0, 3, 6, 9], 24), None);
+    }
+}
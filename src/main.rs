@@ -2,63 +2,737 @@
 extern crate clap;
 extern crate cron_gate;
 
-use chrono::offset::TimeZone;
-use chrono::Local;
-use clap::Arg;
-use cron_gate::expression::{Expression, DATE_FORMAT};
+use chrono::{DateTime, Datelike, Duration, Local, TimeZone, Utc};
+use clap::{AppSettings, Arg, ArgMatches, SubCommand};
+use cron_gate::calendar;
+use cron_gate::clock::{Clock, FixedClock, SystemClock};
+use cron_gate::crontab::Crontab;
+use cron_gate::datetime::{parse_datetime, validate_format};
+use cron_gate::describe::describe;
+use cron_gate::dialect::{Dialect, Schedule, DIALECTS};
+use cron_gate::explain::explain;
+use cron_gate::expression::{CronLine, Expression, DATE_FORMAT};
+use cron_gate::ical;
+use cron_gate::lint::lint;
+use cron_gate::manifest;
+use cron_gate::output::{self, Format, FORMATS};
+use cron_gate::rrule::{from_rrule, to_rrule};
+#[cfg(feature = "runner")]
+use cron_gate::runner::{Options, Outcome, Runner};
+use cron_gate::systemd::{self, from_on_calendar};
+use cron_gate::validate::{self, FileReport, ReportFormat, REPORT_FORMATS};
+use std::fmt::Display;
+
+/// Exit code of invalid arguments, clap's own errors included
+const EXIT_USAGE: i32 = 2;
+/// Exit code of an expression or a file which cannot be parsed
+const EXIT_PARSE: i32 = 3;
+/// Exit code of an expression which never fires
+const EXIT_NEVER_FIRES: i32 = 4;
+/// Exit code of a file which cannot be read
+const EXIT_IO: i32 = 5;
 
 fn main() {
     let app = app_from_crate!()
-        .arg(
-            Arg::with_name("expression")
-                .help("Cron Expression '* * * 7 * [command]'")
-                .required(true),
+        .setting(AppSettings::SubcommandsNegateReqs)
+        // Without a subcommand, it runs `next` as it did before subcommands
+        .args(&next_args())
+        .subcommand(
+            SubCommand::with_name("next")
+                .about("Print the next datetimes (default)")
+                .args(&next_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("prev")
+                .about("Print the previous datetimes, the latest first")
+                .args(&expression_args())
+                .arg(before_arg())
+                .arg(number_arg().default_value("10"))
+                .args(&output_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("between")
+                .about("Print the datetimes after -a and before -b")
+                .args(&expression_args())
+                .arg(after_arg().required(true))
+                .arg(before_arg().required(true))
+                .arg(number_arg())
+                .args(&output_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("check")
+                .about("Check the expression is valid and fires")
+                .args(&expression_args()),
         )
-        .arg(
-            Arg::with_name("after")
-                .help("Dates after 'Y/m/d H:M'")
-                .short("a")
-                .long("after")
-                .takes_value(true),
+        .subcommand(
+            SubCommand::with_name("describe")
+                .about("Describe the expression in English")
+                .args(&expression_args()),
         )
-        .arg(
-            Arg::with_name("number")
-                .help("Displayed number")
-                .short("n")
-                .long("number")
-                .takes_value(true)
-                .default_value("10"),
+        .subcommand(
+            SubCommand::with_name("lint")
+                .about("Warn about suspicious parts of the expression")
+                .args(&expression_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("validate")
+                .about("Validate crontab files, exiting with 1 on invalid entries")
+                .arg(
+                    Arg::with_name("file")
+                        .help("Crontab files")
+                        .value_name("FILE")
+                        .required(true)
+                        .multiple(true),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .help("Report format")
+                        .short("f")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&REPORT_FORMATS)
+                        .default_value("text"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("explain")
+                .about("Print the values each field expands to with notes on steps")
+                .args(&expression_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("cal")
+                .about("Print a month calendar marking days the expression fires on")
+                .args(&expression_args())
+                .arg(
+                    Arg::with_name("month")
+                        .help("Month 'YYYY-MM' [default: this month]")
+                        .long("month")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("heatmap")
+                        .help("Print runs per hour of week instead")
+                        .long("heatmap"),
+                ),
         );
 
-    let matches = app.get_matches();
+    #[cfg(feature = "runner")]
+    let app = app.subcommand(
+        SubCommand::with_name("run")
+            .about("Run the jobs of a crontab as cron does, logging the results")
+            .arg(
+                Arg::with_name("crontab")
+                    .help("Crontab file")
+                    .value_name("FILE")
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("shell")
+                    .help(
+                        "Shell running commands with -c [default: SHELL of the crontab or /bin/sh]",
+                    )
+                    .long("shell")
+                    .takes_value(true),
+            ),
+    );
 
-    let mut after = Local::now();
-    if let Some(a_str) = matches.value_of("after") {
-        match Local.datetime_from_str(a_str, DATE_FORMAT) {
-            Ok(a) => after = a,
-            Err(e) => {
-                eprintln!("Invalid -a value: '{}'", a_str);
-                panic!(e);
-            }
+    let matches = app.get_matches_safe().unwrap_or_else(|e| usage(e));
+    let clock = clock();
+    match matches.subcommand() {
+        ("next", Some(m)) => next(m, &*clock),
+        ("prev", Some(m)) => prev(m, &*clock),
+        ("between", Some(m)) => between(m, &*clock),
+        ("check", Some(m)) => check(m),
+        ("describe", Some(m)) => print_describe(m),
+        ("lint", Some(m)) => print_lint(m),
+        ("validate", Some(m)) => validate(m),
+        ("explain", Some(m)) => print_explain(m),
+        ("cal", Some(m)) => cal(m, &*clock),
+        #[cfg(feature = "runner")]
+        ("run", Some(m)) => run(m),
+        _ => next(&matches, &*clock),
+    }
+}
+
+fn expression_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("expression")
+        .help("Cron Expression '* * * 7 * [command]'")
+        .required(true)
+}
+
+fn after_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("after")
+        .help("Dates after 'Y/m/d H:M', ISO 8601, 'Y-m-d', epoch seconds, 'now', '+2h' or 'tomorrow 09:00'")
+        .short("a")
+        .long("after")
+        .takes_value(true)
+        .allow_hyphen_values(true)
+}
+
+fn before_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("before")
+        .help("Dates before it in the formats of --after")
+        .short("b")
+        .long("before")
+        .takes_value(true)
+        .allow_hyphen_values(true)
+}
+
+fn number_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("number")
+        .help("Displayed number")
+        .short("n")
+        .long("number")
+        .takes_value(true)
+}
+
+fn output_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("format")
+            .help("Output format")
+            .short("f")
+            .long("format")
+            .takes_value(true)
+            .possible_values(&FORMATS)
+            .default_value("text"),
+        Arg::with_name("output-format")
+            .help("strftime format of datetimes in text output")
+            .long("output-format")
+            .takes_value(true)
+            .default_value(DATE_FORMAT),
+    ]
+}
+
+/// Returns the expression with `--dialect` and `--seed` to parse it
+fn expression_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![expression_arg(), dialect_arg(), seed_arg()]
+}
+
+fn dialect_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("dialect")
+        .help("Dialect of the expression, evaluated in UTC")
+        .long("dialect")
+        .takes_value(true)
+        .possible_values(&DIALECTS)
+}
+
+fn seed_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("seed")
+        .help("Seed of H in the jenkins dialect (the job name), or of '~' like '0~30' (a number)")
+        .long("seed")
+        .value_name("SEED")
+        .takes_value(true)
+}
+
+fn next_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    let mut args = vec![
+        expression_arg().required(false).required_unless_one(&[
+            "crontab",
+            "from-rrule",
+            "from-on-calendar",
+            "manifest",
+        ]),
+        after_arg(),
+        number_arg().default_value("10"),
+    ];
+    args.extend(output_args());
+    args.extend(vec![
+        Arg::with_name("crontab")
+            .help("Crontab file to export instead of the expression, only with '-f ics'")
+            .long("crontab")
+            .takes_value(true)
+            .conflicts_with("expression"),
+        Arg::with_name("duration")
+            .help("Minutes of each event in ics output")
+            .long("duration")
+            .takes_value(true)
+            .default_value("15"),
+        Arg::with_name("from-rrule")
+            .help("RRULE to preview instead of the expression like 'FREQ=WEEKLY;BYDAY=MO;BYHOUR=9'")
+            .long("from-rrule")
+            .takes_value(true)
+            .conflicts_with_all(&["expression", "crontab"]),
+        Arg::with_name("to-rrule")
+            .help("Print the RRULE equivalent to the expression")
+            .long("to-rrule"),
+        Arg::with_name("from-on-calendar")
+            .help("systemd OnCalendar spec to preview instead of the expression like 'Mon..Fri 09:30'")
+            .long("from-on-calendar")
+            .takes_value(true)
+            .conflicts_with_all(&["expression", "crontab", "from-rrule"]),
+        Arg::with_name("to-systemd")
+            .help("Print NAME.timer and NAME.service units running the expression")
+            .long("to-systemd")
+            .value_name("NAME")
+            .takes_value(true),
+        Arg::with_name("manifest")
+            .help("Kubernetes CronJob manifests or GitHub Actions workflows to validate and preview")
+            .long("manifest")
+            .value_name("FILE")
+            .takes_value(true)
+            .multiple(true)
+            .conflicts_with_all(&["expression", "crontab", "from-rrule", "from-on-calendar"]),
+        dialect_arg().conflicts_with_all(&[
+            "crontab",
+            "from-rrule",
+            "from-on-calendar",
+            "manifest",
+            "to-rrule",
+            "to-systemd",
+        ]),
+        seed_arg(),
+        Arg::with_name("rrule")
+            .help("Export a recurring event with an RRULE instead of the next occurrences in ics output")
+            .long("rrule"),
+    ]);
+    args
+}
+
+fn next(matches: &ArgMatches, clock: &dyn Clock) {
+    let now = clock.now();
+    let expression_str = expression_str_of(matches);
+    let after = datetime_of(matches, "after", now);
+    let number = number_of(matches);
+    let format = value_t!(matches, "format", Format).unwrap_or_else(|e| usage(e));
+
+    if let Some(paths) = matches.values_of("manifest") {
+        print_manifests(paths, after, number, output_format_of(matches));
+    } else if format == Format::Ics && !matches.is_present("dialect") {
+        print_ics(matches, &expression_str, after, number, now);
+    } else if matches.is_present("crontab") {
+        exit_with(EXIT_USAGE, "--crontab is only supported with '-f ics'");
+    } else if matches.is_present("to-rrule") || matches.is_present("to-systemd") {
+        print_translation(matches, &expression_with_seed(matches, &expression_str));
+    } else {
+        let listed = listed_of(matches, &expression_str);
+        let lines = listed.next(after, number);
+        print_lines(&lines, &listed.schedule.expression, matches);
+    }
+}
+
+fn prev(matches: &ArgMatches, clock: &dyn Clock) {
+    let listed = listed_of(matches, matches.value_of("expression").unwrap_or(""));
+    let before = datetime_of(matches, "before", clock.now());
+    let lines = listed.prev(before, number_of(matches));
+    print_lines(&lines, &listed.schedule.expression, matches);
+}
+
+fn between(matches: &ArgMatches, clock: &dyn Clock) {
+    let listed = listed_of(matches, matches.value_of("expression").unwrap_or(""));
+    let now = clock.now();
+    let after = datetime_of(matches, "after", now);
+    let before = datetime_of(matches, "before", now);
+    // Every datetime in the range unless --number is given
+    let number = match matches.value_of("number") {
+        Some(_) => number_of(matches),
+        None => usize::MAX,
+    };
+    let lines = listed.between(after, before, number);
+    print_lines(&lines, &listed.schedule.expression, matches);
+}
+
+/// The expression of `next`, `prev` or `between`, in the dialect of `--dialect` if it is given
+struct Listed {
+    schedule: Schedule,
+    /// Whether the schedule is evaluated in UTC, as dialects are
+    utc: bool,
+}
+
+impl Listed {
+    fn next(&self, after: DateTime<Local>, number: usize) -> Vec<CronLine> {
+        self.lines(if self.utc {
+            to_local(
+                self.schedule
+                    .earler_excuting_datetimes(after.with_timezone(&Utc), number),
+            )
+        } else {
+            self.schedule.earler_excuting_datetimes(after, number)
+        })
+    }
+
+    fn prev(&self, before: DateTime<Local>, number: usize) -> Vec<CronLine> {
+        self.lines(if self.utc {
+            to_local(
+                self.schedule
+                    .previous_datetimes(before.with_timezone(&Utc), number),
+            )
+        } else {
+            self.schedule.previous_datetimes(before, number)
+        })
+    }
+
+    fn between(
+        &self,
+        after: DateTime<Local>,
+        before: DateTime<Local>,
+        number: usize,
+    ) -> Vec<CronLine> {
+        self.lines(if self.utc {
+            to_local(self.schedule.datetimes_between(
+                after.with_timezone(&Utc),
+                before.with_timezone(&Utc),
+                number,
+            ))
+        } else {
+            self.schedule.datetimes_between(after, before, number)
+        })
+    }
+
+    fn lines(&self, datetimes: Vec<DateTime<Local>>) -> Vec<CronLine> {
+        datetimes
+            .into_iter()
+            .map(|datetime| CronLine {
+                datetime,
+                command: self.schedule.expression.command.clone(),
+            })
+            .collect()
+    }
+}
+
+fn to_local<Tz: TimeZone>(datetimes: Vec<DateTime<Tz>>) -> Vec<DateTime<Local>> {
+    datetimes
+        .into_iter()
+        .map(|d| d.with_timezone(&Local))
+        .collect()
+}
+
+/// Returns the expression of the listing subcommand which fires, or exits
+fn listed_of(matches: &ArgMatches, text: &str) -> Listed {
+    let schedule = schedule_of(matches, text);
+    if let Some(reason) = schedule.expression.never_fires_reason() {
+        exit_with(EXIT_NEVER_FIRES, reason);
+    }
+    Listed {
+        schedule,
+        utc: matches.is_present("dialect"),
+    }
+}
+
+/// Returns the schedule of the text in the dialect of `--dialect`, or of the expression
+/// with '~' chosen by `--seed`, after printing the warnings of the dialect, or exits
+fn schedule_of(matches: &ArgMatches, text: &str) -> Schedule {
+    let schedule = if matches.is_present("dialect") {
+        let dialect = value_t!(matches, "dialect", Dialect).unwrap_or_else(|e| usage(e));
+        dialect
+            .parse_with_seed(text, matches.value_of("seed").unwrap_or(""))
+            .unwrap_or_else(|e| exit_with(EXIT_PARSE, e))
+    } else {
+        Schedule {
+            expression: expression_with_seed(matches, text),
+            day_or: false,
+            years: None,
+            warnings: vec![],
         }
+    };
+    for warning in schedule.warnings.iter() {
+        eprintln!("Warning: {}", warning);
     }
+    schedule
+}
 
-    let mut number = 10;
-    if let Some(n_str) = matches.value_of("number") {
-        if let Ok(n) = n_str.parse::<usize>() {
-            number = n;
+/// Returns the expression or the translation of `--from-rrule` or `--from-on-calendar`
+/// after printing the warnings of the translation, or exits
+fn expression_str_of(matches: &ArgMatches) -> String {
+    let translation = match matches.value_of("from-rrule") {
+        Some(rule) => from_rrule(rule),
+        None => match matches.value_of("from-on-calendar") {
+            Some(spec) => from_on_calendar(spec),
+            None => return matches.value_of("expression").unwrap_or("").to_string(),
+        },
+    };
+    match translation {
+        Ok(t) => {
+            for warning in t.warnings.iter() {
+                eprintln!("Warning: {}", warning);
+            }
+            t.value.to_string()
         }
+        Err(e) => exit_with(EXIT_PARSE, e),
     }
+}
+
+/// Returns the expression with the values of '~' chosen by `--seed`
+/// after printing them, or exits
+fn expression_with_seed(matches: &ArgMatches, text: &str) -> Expression {
+    let seed = match matches.value_of("seed") {
+        Some(_) => value_t!(matches, "seed", u64).unwrap_or_else(|e| usage(e)),
+        None => 0,
+    };
+    let expression = Expression::with_seed(text, seed).unwrap_or_else(|e| exit_with(EXIT_PARSE, e));
+    if text.contains('~') {
+        eprintln!("Chosen by seed {}: {:#}", seed, expression);
+    }
+    expression
+}
 
-    if let Some(o) = matches.value_of("expression") {
-        match Expression::new(o) {
-            Ok(exp) => {
-                let datetimes = exp.executing_dates(after, number);
-                for dt in datetimes {
-                    println!("{}", dt);
+/// Prints the next datetimes of the jobs in the manifests, exiting if any job is invalid
+fn print_manifests<'a, I: Iterator<Item = &'a str>>(
+    paths: I,
+    after: DateTime<Local>,
+    number: usize,
+    output_format: &str,
+) {
+    let mut valid = true;
+    for path in paths {
+        let jobs = manifest::extract(&read(path))
+            .unwrap_or_else(|e| exit_with(EXIT_PARSE, format!("{}: {}", path, e)));
+        for job in jobs {
+            match job.check() {
+                Ok((schedule, tz)) => {
+                    println!("{} '{}' ({})", job.name, job.spec, tz.name());
+                    for warning in schedule.warnings.iter() {
+                        println!("  Warning: {}", warning);
+                    }
+                    let from = after.with_timezone(&tz);
+                    for datetime in schedule.earler_excuting_datetimes(from, number) {
+                        println!("  {}", datetime.format(output_format));
+                    }
                 }
+                Err(e) => {
+                    valid = false;
+                    eprintln!("{}: {} '{}': {}", path, job.name, job.spec, e);
+                }
+            }
+        }
+    }
+    if !valid {
+        std::process::exit(EXIT_PARSE);
+    }
+}
+
+/// Prints the calendar of the expression, or of the jobs of `--crontab`
+fn print_ics(
+    matches: &ArgMatches,
+    expression_str: &str,
+    after: DateTime<Local>,
+    number: usize,
+    now: DateTime<Local>,
+) {
+    let duration = value_t!(matches, "duration", i64).unwrap_or_else(|e| usage(e));
+    let options = ical::Options {
+        duration: Duration::minutes(duration),
+        recurring: matches.is_present("rrule"),
+        stamp: now.with_timezone(&Utc),
+    };
+    let expressions = match matches.value_of("crontab") {
+        Some(path) => {
+            let crontab = Crontab::parse(&read(path));
+            for (line, e) in crontab.errors.iter().chain(crontab.unsupported.iter()) {
+                eprintln!("{}:{}: {}", path, line, e);
             }
-            Err(e) => panic!(e),
+            crontab.entries.into_iter().map(|e| e.expression).collect()
+        }
+        None => vec![expression_with_seed(matches, expression_str)],
+    };
+    print!("{}", ical::export(&expressions, after, number, &options));
+}
+
+/// Prints the RRULE of `--to-rrule` or the units of `--to-systemd`, or exits
+fn print_translation(matches: &ArgMatches, expression: &Expression) {
+    if matches.is_present("to-rrule") {
+        match to_rrule(expression) {
+            Ok(rule) => println!("{}", rule),
+            Err(reason) => exit_with(EXIT_PARSE, reason),
+        }
+        return;
+    }
+    match systemd::units(expression, &[]) {
+        Ok(units) => {
+            let name = matches.value_of("to-systemd").unwrap_or("cron_gate");
+            print!(
+                "# {}.timer\n{}\n# {}.service\n{}",
+                name, units.timer, name, units.service
+            );
+        }
+        Err(reason) => exit_with(EXIT_PARSE, reason),
+    }
+}
+
+fn check(matches: &ArgMatches) {
+    let expression = expression_of(matches);
+    println!("OK: {}", expression.schedule());
+}
+
+fn print_describe(matches: &ArgMatches) {
+    println!("{}", describe(&expression_of(matches)));
+}
+
+fn print_lint(matches: &ArgMatches) {
+    let warnings = lint(&expression_of_any(matches));
+    for warning in warnings.iter() {
+        println!("Warning: {}", warning);
+    }
+    if !warnings.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+fn validate(matches: &ArgMatches) {
+    let format = value_t!(matches, "format", ReportFormat).unwrap_or_else(|e| usage(e));
+    let reports: Vec<FileReport> = matches
+        .values_of("file")
+        .into_iter()
+        .flatten()
+        .map(|path| match std::fs::read_to_string(path) {
+            Ok(text) => FileReport::check(path, &text),
+            Err(e) => FileReport::unreadable(path, &format!("Cannot read {}: {}", path, e)),
+        })
+        .collect();
+    print!("{}", validate::render(&reports, format));
+    if reports.iter().any(|r| r.unreadable) {
+        std::process::exit(EXIT_IO);
+    }
+    if reports.iter().any(|r| !r.problems.is_empty()) {
+        std::process::exit(1);
+    }
+}
+
+fn print_explain(matches: &ArgMatches) {
+    print!("{}", explain(&expression_of(matches)));
+}
+
+fn cal(matches: &ArgMatches, clock: &dyn Clock) {
+    let (year, month) = match matches.value_of("month") {
+        Some(text) => calendar::parse_month(text).unwrap_or_else(|e| exit_with(EXIT_USAGE, e)),
+        None => {
+            let today = clock.now();
+            (today.year(), today.month())
         }
+    };
+    let expression = expression_of_any(matches);
+    if let Some(reason) = expression.never_fires_reason() {
+        eprintln!("{}", reason);
+    }
+    if matches.is_present("heatmap") {
+        print!("{}", calendar::heatmap(&expression, year, month));
+    } else {
+        print!("{}", calendar::month(&expression, year, month));
+    }
+}
+
+#[cfg(feature = "runner")]
+fn run(matches: &ArgMatches) {
+    let path = matches.value_of("crontab").unwrap_or("");
+    let crontab = Crontab::parse(&read(path));
+    for (line, e) in crontab.errors.iter().chain(crontab.unsupported.iter()) {
+        eprintln!("{}:{}: {}", path, line, e.replace('\n', ": "));
+    }
+    let options = Options {
+        shell: matches.value_of("shell").map(|s| s.to_string()),
+    };
+    let runner = Runner::new(&crontab, &options);
+    eprintln!(
+        "Running {} jobs of {} with {}",
+        runner.jobs().len(),
+        path,
+        runner.shell()
+    );
+    // Jobs run on the system clock even if `CRON_GATE_NOW` is set
+    if let Err(e) = runner.run(&SystemClock, log) {
+        exit_with(EXIT_NEVER_FIRES, e);
     }
 }
+
+#[cfg(feature = "runner")]
+fn log(outcome: &Outcome) {
+    println!("{}", outcome.summary());
+    for line in outcome.stdout.lines() {
+        println!("  {}", line);
+    }
+    for line in outcome.stderr.lines() {
+        eprintln!("  {}", line);
+    }
+}
+
+/// Returns the expression which fires, or exits
+fn expression_of(matches: &ArgMatches) -> Expression {
+    let expression = expression_of_any(matches);
+    if let Some(reason) = expression.never_fires_reason() {
+        exit_with(EXIT_NEVER_FIRES, reason);
+    }
+    expression
+}
+
+/// Returns the expression even if it never fires, or exits
+fn expression_of_any(matches: &ArgMatches) -> Expression {
+    schedule_of(matches, matches.value_of("expression").unwrap_or("")).expression
+}
+
+/// Returns the datetime of the argument relative to now, or now if it is absent
+fn datetime_of(matches: &ArgMatches, name: &str, now: DateTime<Local>) -> DateTime<Local> {
+    match matches.value_of(name) {
+        Some(text) => match parse_datetime(text, now) {
+            Ok(datetime) => datetime,
+            Err(e) => exit_with(
+                EXIT_USAGE,
+                format!("Invalid --{} value '{}': {}", name, text, e),
+            ),
+        },
+        None => now,
+    }
+}
+
+fn number_of(matches: &ArgMatches) -> usize {
+    match matches.value_of("number") {
+        Some(text) => match text.parse::<usize>() {
+            Ok(number) if number > 0 => number,
+            _ => exit_with(
+                EXIT_USAGE,
+                format!(
+                    "Invalid --number value '{}': should be a positive integer",
+                    text
+                ),
+            ),
+        },
+        None => 10,
+    }
+}
+
+fn output_format_of<'a>(matches: &'a ArgMatches) -> &'a str {
+    let output_format = matches.value_of("output-format").unwrap_or(DATE_FORMAT);
+    if let Err(e) = validate_format(output_format) {
+        exit_with(EXIT_USAGE, e);
+    }
+    output_format
+}
+
+fn print_lines(lines: &[CronLine], expression: &Expression, matches: &ArgMatches) {
+    let format = value_t!(matches, "format", Format).unwrap_or_else(|e| usage(e));
+    let output_format = output_format_of(matches);
+    match format {
+        Format::Text => print!("{}", output::render_text(lines, output_format)),
+        _ => print!("{}", output::render(lines, expression, format)),
+    }
+}
+
+/// Returns the clock stopped at `CRON_GATE_NOW` if it is set, for reproducible output
+fn clock() -> Box<dyn Clock> {
+    match std::env::var("CRON_GATE_NOW") {
+        Ok(text) => match parse_datetime(&text, Local::now()) {
+            Ok(now) => Box::new(FixedClock(now)),
+            Err(e) => exit_with(
+                EXIT_USAGE,
+                format!("Invalid CRON_GATE_NOW value '{}': {}", text, e),
+            ),
+        },
+        Err(_) => Box::new(SystemClock),
+    }
+}
+
+fn read(path: &str) -> String {
+    std::fs::read_to_string(path)
+        .unwrap_or_else(|e| exit_with(EXIT_IO, format!("Cannot read {}: {}", path, e)))
+}
+
+/// Prints the help or the version as clap does, and exits with `EXIT_USAGE` on errors
+fn usage(e: clap::Error) -> ! {
+    if !e.use_stderr() {
+        e.exit();
+    }
+    exit_with(EXIT_USAGE, e.message)
+}
+
+fn exit_with<T: Display>(code: i32, message: T) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(code);
+}
@@ -0,0 +1,200 @@
+//! Cron specs in Kubernetes manifests and GitHub Actions workflows,
+//! enabled by the `yaml` feature
+//!
+//! ```
+//! use chrono::{TimeZone, Utc};
+//! use cron_gate::manifest::extract;
+//!
+//! let yaml = "
+//! apiVersion: batch/v1
+//! kind: CronJob
+//! metadata:
+//!   name: backup
+//! spec:
+//!   schedule: '0 3 * * *'
+//!   timeZone: Asia/Tokyo
+//! ";
+//! let jobs = extract(yaml).unwrap();
+//! assert_eq!(jobs[0].name, "CronJob/backup");
+//!
+//! let (schedule, tz) = jobs[0].check().unwrap();
+//! let after = Utc.ymd(2019, 5, 28).and_hms(0, 0, 0);
+//! let next = schedule.earler_excuting_datetimes(after.with_timezone(&tz), 1);
+//! assert_eq!(next[0].to_rfc3339(), "2019-05-29T03:00:00+09:00");
+//! ```
+
+use crate::dialect::{Dialect, Schedule};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use serde_yaml::Value;
+
+/// A cron spec found in YAML
+#[derive(Debug, PartialEq, Clone)]
+pub struct Job {
+    /// `CronJob/<metadata.name>` or `<workflow name> schedule[<index>]`
+    pub name: String,
+    pub dialect: Dialect,
+    pub spec: String,
+    /// `spec.timeZone` of a CronJob
+    pub time_zone: Option<String>,
+}
+
+impl Job {
+    /// Returns the schedule validated in the dialect and the time zone it runs in
+    pub fn check(&self) -> Result<(Schedule, Tz), String> {
+        let mut schedule = self.dialect.parse(&self.spec)?;
+        let tz = match (self.dialect, &self.time_zone) {
+            (Dialect::Kubernetes, Some(name)) => name
+                .parse::<Tz>()
+                .map_err(|e| format!("Invalid spec.timeZone '{}': {}", name, e))?,
+            (Dialect::Kubernetes, None) => {
+                schedule.warnings.push(
+                    "spec.timeZone is unset, so the time zone of kube-controller-manager is used: UTC is assumed"
+                        .to_string(),
+                );
+                chrono_tz::UTC
+            }
+            _ => chrono_tz::UTC,
+        };
+        Ok((schedule, tz))
+    }
+}
+
+/// Returns cron specs of CronJobs and workflows in all documents of the YAML
+///
+/// Other documents are skipped.
+pub fn extract(text: &str) -> Result<Vec<Job>, String> {
+    let mut jobs = vec![];
+    for document in serde_yaml::Deserializer::from_str(text) {
+        let value = Value::deserialize(document).map_err(|e| e.to_string())?;
+        if value.get("kind").and_then(Value::as_str) == Some("CronJob") {
+            jobs.push(cron_job(&value));
+        } else if let Some(on) = workflow_on(&value) {
+            jobs.extend(workflow(&value, on));
+        }
+    }
+    Ok(jobs)
+}
+
+fn cron_job(value: &Value) -> Job {
+    let name = value
+        .get("metadata")
+        .and_then(|m| m.get("name"))
+        .and_then(Value::as_str)
+        .unwrap_or("");
+    let spec = value.get("spec");
+    let field = |key: &str| {
+        spec.and_then(|s| s.get(key))
+            .and_then(Value::as_str)
+            .map(|s| s.to_string())
+    };
+    Job {
+        name: format!("CronJob/{}", name),
+        dialect: Dialect::Kubernetes,
+        spec: field("schedule").unwrap_or_default(),
+        time_zone: field("timeZone"),
+    }
+}
+
+/// Returns `on` of a workflow, which YAML 1.1 parsers may read as `true`
+fn workflow_on(value: &Value) -> Option<&Value> {
+    let mapping = value.as_mapping()?;
+    mapping
+        .get(&Value::String("on".to_string()))
+        .or_else(|| mapping.get(&Value::Bool(true)))
+}
+
+fn workflow(value: &Value, on: &Value) -> Vec<Job> {
+    let name = value
+        .get("name")
+        .and_then(Value::as_str)
+        .unwrap_or("workflow");
+    let schedules = match on.get("schedule").and_then(Value::as_sequence) {
+        Some(s) => s,
+        None => return vec![],
+    };
+    schedules
+        .iter()
+        .enumerate()
+        .map(|(i, s)| Job {
+            name: format!("{} schedule[{}]", name, i),
+            dialect: Dialect::GithubActions,
+            spec: s
+                .get("cron")
+                .and_then(Value::as_str)
+                .unwrap_or("")
+                .to_string(),
+            time_zone: None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFESTS: &str = "
+apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: config
+---
+apiVersion: batch/v1
+kind: CronJob
+metadata:
+  name: report
+spec:
+  schedule: '@daily'
+---
+apiVersion: batch/v1
+kind: CronJob
+metadata:
+  name: broken
+spec:
+  schedule: '0 0 * * *'
+  timeZone: Mars/Olympus
+";
+
+    const WORKFLOW: &str = "
+name: nightly
+on:
+  push:
+    branches: [main]
+  schedule:
+    - cron: '30 5 * * 1-5'
+    - cron: '*/1 * * * *'
+jobs: {}
+";
+
+    #[test]
+    fn test_extract_cron_jobs() {
+        let jobs = extract(MANIFESTS).unwrap();
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[0].name, "CronJob/report");
+        assert_eq!(jobs[0].spec, "@daily");
+        assert_eq!(jobs[0].time_zone, None);
+
+        let (schedule, tz) = jobs[0].check().unwrap();
+        assert_eq!(schedule.expression.schedule(), "0 0 * * *");
+        assert_eq!(schedule.warnings.len(), 1);
+        assert_eq!(tz, chrono_tz::UTC);
+
+        assert!(jobs[1].check().is_err());
+    }
+
+    #[test]
+    fn test_extract_workflow() {
+        let jobs = extract(WORKFLOW).unwrap();
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[0].name, "nightly schedule[0]");
+        assert_eq!(jobs[0].dialect, Dialect::GithubActions);
+        assert!(jobs[0].check().unwrap().0.warnings.is_empty());
+        assert_eq!(jobs[1].check().unwrap().0.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_extract_invalid_yaml() {
+        assert!(extract("a: [").is_err());
+        assert!(extract("").unwrap().is_empty());
+    }
+}
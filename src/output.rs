@@ -0,0 +1,261 @@
+use crate::expression::{CronLine, Expression, DATE_FORMAT};
+use crate::ical;
+use std::str::FromStr;
+
+/// Names of the formats accepted by `Format::from_str`
+pub const FORMATS: [&str; 6] = ["text", "json", "ndjson", "csv", "tsv", "ics"];
+
+const COLUMNS: [&str; 4] = ["datetime", "epoch", "command", "expression"];
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Format {
+    /// `Y/m/d H:M command` per line, or in a given strftime format
+    Text,
+    /// An array of objects
+    Json,
+    /// An object per line
+    Ndjson,
+    Csv,
+    Tsv,
+    /// iCalendar having a VEVENT per line
+    Ics,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "ndjson" => Ok(Format::Ndjson),
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            "ics" => Ok(Format::Ics),
+            _ => Err(format!(
+                "Unknown format '{}': should be one of {}",
+                s,
+                FORMATS.join(", ")
+            )),
+        }
+    }
+}
+
+/// Returns the lines rendered in the format, each of which ends with a newline
+///
+/// Machine-readable formats have the datetime in RFC 3339 with its offset,
+/// the Unix epoch seconds, the command and the schedule of the expression.
+/// Text has the datetime in `DATE_FORMAT`; use `render_text` for another format.
+/// Ics has events of the default `ical::Options`; use `ical::export` for others.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::{Local, TimeZone, Utc};
+/// use cron_gate::expression::{CronLine, Expression};
+/// use cron_gate::output::{render, Format};
+///
+/// let e = Expression::new("0 9 * * * echo \"Yo\"").unwrap();
+/// let datetime = Utc.ymd(2019, 5, 28).and_hms(0, 0, 0).with_timezone(&Local);
+/// let lines = [CronLine { datetime, command: e.command.clone() }];
+///
+/// assert_eq!(
+///     render(&lines, &e, Format::Ndjson),
+///     format!(
+///         "{{\"datetime\":\"{}\",\"epoch\":1559001600,\"command\":\"echo \\\"Yo\\\"\",\"expression\":\"0 9 * * *\"}}\n",
+///         datetime.to_rfc3339()
+///     )
+/// );
+/// assert_eq!(
+///     render(&lines, &e, Format::Csv),
+///     format!(
+///         "datetime,epoch,command,expression\n{},1559001600,\"echo \"\"Yo\"\"\",0 9 * * *\n",
+///         datetime.to_rfc3339()
+///     )
+/// );
+/// ```
+pub fn render(lines: &[CronLine], expression: &Expression, format: Format) -> String {
+    let schedule = expression.schedule();
+    let rows: Vec<[String; 4]> = lines
+        .iter()
+        .map(|line| {
+            [
+                line.datetime.to_rfc3339(),
+                line.datetime.timestamp().to_string(),
+                line.command.clone(),
+                schedule.clone(),
+            ]
+        })
+        .collect();
+
+    match format {
+        Format::Text => render_text(lines, DATE_FORMAT),
+        Format::Json => {
+            if rows.is_empty() {
+                return "[]\n".to_string();
+            }
+            let objects: Vec<String> = rows
+                .iter()
+                .map(|r| format!("  {}", json_object(r)))
+                .collect();
+            format!("[\n{}\n]\n", objects.join(",\n"))
+        }
+        Format::Ndjson => rows
+            .iter()
+            .map(|r| format!("{}\n", json_object(r)))
+            .collect(),
+        Format::Csv => delimited(&rows, ",", escape_csv),
+        Format::Tsv => delimited(&rows, "\t", escape_tsv),
+        Format::Ics => ical::calendar(&ical::events_of(
+            lines,
+            expression,
+            &ical::Options::default(),
+        )),
+    }
+}
+
+/// Returns the lines having the datetime in the strftime format
+///
+/// # Examples
+///
+/// ```
+/// use chrono::Local;
+/// use chrono::offset::TimeZone;
+/// use cron_gate::expression::Expression;
+/// use cron_gate::output::render_text;
+///
+/// let e = Expression::new("0 9 * * * command").unwrap();
+/// let from = Local.datetime_from_str("2019/5/28 0:0", "%Y/%m/%d %H:%M").unwrap();
+/// assert_eq!(
+///     render_text(&e.executing_dates(from, 2), "%m-%d %H:%M"),
+///     "05-28 09:00 command\n05-29 09:00 command\n"
+/// );
+/// ```
+pub fn render_text(lines: &[CronLine], datetime_format: &str) -> String {
+    lines
+        .iter()
+        .map(|line| format!("{}\n", line.format(datetime_format)))
+        .collect()
+}
+
+fn json_object(row: &[String; 4]) -> String {
+    format!(
+        "{{\"{}\":\"{}\",\"{}\":{},\"{}\":\"{}\",\"{}\":\"{}\"}}",
+        COLUMNS[0],
+        escape_json(&row[0]),
+        COLUMNS[1],
+        row[1],
+        COLUMNS[2],
+        escape_json(&row[2]),
+        COLUMNS[3],
+        escape_json(&row[3])
+    )
+}
+
+fn delimited(rows: &[[String; 4]], separator: &str, escape: fn(&str) -> String) -> String {
+    let mut ret = format!("{}\n", COLUMNS.join(separator));
+    for row in rows {
+        let cells: Vec<String> = row.iter().map(|c| escape(c)).collect();
+        ret.push_str(&cells.join(separator));
+        ret.push('\n');
+    }
+    ret
+}
+
+pub(crate) fn escape_json(text: &str) -> String {
+    let mut ret = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            '\n' => ret.push_str("\\n"),
+            '\r' => ret.push_str("\\r"),
+            '\t' => ret.push_str("\\t"),
+            c if (c as u32) < 0x20 => ret.push_str(&format!("\\u{:04x}", c as u32)),
+            c => ret.push(c),
+        }
+    }
+    ret
+}
+
+/// Quotes the cell as RFC 4180 if needed
+fn escape_csv(text: &str) -> String {
+    if text.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// TSV cannot contain tabs and newlines, so they are escaped like `\t`
+fn escape_tsv(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::DATE_FORMAT;
+    use chrono::offset::TimeZone;
+    use chrono::Local;
+
+    fn lines(e: &Expression) -> Vec<CronLine> {
+        let from = Local
+            .datetime_from_str("2019/5/28 0:0", DATE_FORMAT)
+            .unwrap();
+        e.executing_dates(from, 2)
+    }
+
+    #[test]
+    fn test_from_str() {
+        for name in FORMATS.iter() {
+            assert!(name.parse::<Format>().is_ok());
+        }
+        assert!("xml".parse::<Format>().is_err());
+    }
+
+    #[test]
+    fn test_render_text() {
+        let e = Expression::new("0 9 * * * command").unwrap();
+        assert_eq!(
+            render(&lines(&e), &e, Format::Text),
+            "2019/05/28 09:00 command\n2019/05/29 09:00 command\n"
+        );
+    }
+
+    #[test]
+    fn test_render_json() {
+        let e = Expression::new("0 9 * * * command").unwrap();
+        let lines = lines(&e);
+        let json = render(&lines, &e, Format::Json);
+        assert!(json.starts_with("[\n  {\"datetime\":\""));
+        assert!(json.ends_with("\"command\":\"command\",\"expression\":\"0 9 * * *\"}\n]\n"));
+        assert_eq!(json.lines().count(), 4);
+        assert_eq!(render(&[], &e, Format::Json), "[]\n");
+    }
+
+    #[test]
+    fn test_render_tsv() {
+        let e = Expression::new("0 9 * * * printf 'a\\tb'").unwrap();
+        let tsv = render(&lines(&e), &e, Format::Tsv);
+        let rows: Vec<&str> = tsv.lines().collect();
+        assert_eq!(rows[0], "datetime\tepoch\tcommand\texpression");
+        assert!(rows[1].ends_with("\tprintf 'a\\\\tb'\t0 9 * * *"));
+        assert_eq!(render(&[], &e, Format::Tsv).lines().count(), 1);
+    }
+
+    #[test]
+    fn test_escape_json() {
+        assert_eq!(escape_json("a\"b\\c\nd\u{1}"), "a\\\"b\\\\c\\nd\\u0001");
+    }
+
+    #[test]
+    fn test_escape_csv() {
+        assert_eq!(escape_csv("abc"), "abc");
+        assert_eq!(escape_csv("a,b"), "\"a,b\"");
+        assert_eq!(escape_csv("a\"b"), "\"a\"\"b\"");
+    }
+}
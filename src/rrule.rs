@@ -0,0 +1,433 @@
+//! Translation between cron expressions and iCalendar (RFC 5545) RRULEs
+//!
+//! Both have AND semantics for BYMONTHDAY and BYDAY in this crate,
+//! so every satisfiable expression has an equivalent RRULE.
+//! The opposite direction reports parts cron cannot express.
+
+use crate::compact::CompactExpression;
+use crate::expression::Expression;
+use crate::translation::Translation;
+use std::collections::HashMap;
+
+const WEEKDAYS: [&str; 7] = ["SU", "MO", "TU", "WE", "TH", "FR", "SA"];
+const FREQUENCIES: [&str; 6] = ["MINUTELY", "HOURLY", "DAILY", "WEEKLY", "MONTHLY", "YEARLY"];
+
+/// Returns the RRULE equivalent to the schedule of the expression
+///
+/// The command is not a part of an RRULE.
+/// DTSTART of the rule must be an occurrence, otherwise RFC 5545 counts it as an extra one.
+/// It is an error if the expression never fires.
+///
+/// # Examples
+///
+/// ```
+/// use cron_gate::expression::Expression;
+/// use cron_gate::rrule::to_rrule;
+///
+/// let rule = |text| to_rrule(&Expression::new(text).unwrap());
+/// assert_eq!(rule("0 9 * * 1,3").unwrap(), "FREQ=WEEKLY;BYDAY=MO,WE;BYHOUR=9;BYMINUTE=0");
+/// assert_eq!(rule("*/15 * * * *").unwrap(), "FREQ=HOURLY;BYMINUTE=0,15,30,45");
+/// assert_eq!(rule("30 9 1,15 * *").unwrap(), "FREQ=MONTHLY;BYMONTHDAY=1,15;BYHOUR=9;BYMINUTE=30");
+/// assert!(rule("0 0 30 2 *").is_err());
+/// ```
+pub fn to_rrule(expression: &Expression) -> Result<String, String> {
+    if let Some(reason) = expression.never_fires_reason() {
+        return Err(reason);
+    }
+
+    let e = expression;
+    let full_minute = e.minute_vec.len() == 60;
+    let full_hour = e.hour_vec.len() == 24;
+    let full_date = e.date_vec.len() == 31;
+    let full_month = e.month_vec.len() == 12;
+    let mut weekdays: Vec<u32> = e.day_vec.iter().map(|d| d % 7).collect();
+    weekdays.sort_unstable();
+    weekdays.dedup();
+    let full_day = weekdays.len() == 7;
+
+    let by_minute = format!("BYMINUTE={}", join(&e.minute_vec));
+    let by_hour = format!("BYHOUR={}", join(&e.hour_vec));
+    let by_date = format!("BYMONTHDAY={}", join(&e.date_vec));
+    let by_day = format!(
+        "BYDAY={}",
+        weekdays
+            .iter()
+            .map(|d| WEEKDAYS[*d as usize])
+            .collect::<Vec<&str>>()
+            .join(",")
+    );
+
+    // Parts left to DTSTART would be fixed to it, so every BY* part
+    // below the frequency is listed even if the field is '*'
+    let parts: Vec<String> = if full_minute && full_hour && full_date && full_month && full_day {
+        vec!["FREQ=MINUTELY".to_string()]
+    } else if full_hour && full_date && full_month && full_day {
+        vec!["FREQ=HOURLY".to_string(), by_minute]
+    } else if full_date && full_month && full_day {
+        vec!["FREQ=DAILY".to_string(), by_hour, by_minute]
+    } else if full_date && full_month {
+        vec!["FREQ=WEEKLY".to_string(), by_day, by_hour, by_minute]
+    } else {
+        let mut parts = if full_month {
+            vec!["FREQ=MONTHLY".to_string()]
+        } else {
+            vec![
+                "FREQ=YEARLY".to_string(),
+                format!("BYMONTH={}", join(&e.month_vec)),
+            ]
+        };
+        // BYMONTHDAY is needed unless BYDAY alone selects the days
+        if !full_date || full_day {
+            parts.push(by_date);
+        }
+        if !full_day {
+            parts.push(by_day);
+        }
+        parts.push(by_hour);
+        parts.push(by_minute);
+        parts
+    };
+    Ok(parts.join(";"))
+}
+
+/// Returns the expression equivalent to the RRULE with `[command]` as its command
+///
+/// Parts taken from DTSTART, a step by INTERVAL, COUNT and UNTIL are approximated
+/// with warnings. Parts cron has no counterpart of, like ordinal weekdays (`-1FR`),
+/// negative days, BYSETPOS, BYYEARDAY and BYWEEKNO, are errors.
+///
+/// # Examples
+///
+/// ```
+/// use cron_gate::rrule::from_rrule;
+///
+/// let t = from_rrule("FREQ=WEEKLY;BYDAY=MO,WE;BYHOUR=9;BYMINUTE=0").unwrap();
+/// assert_eq!(t.value.schedule(), "0 9 * * 1,3");
+/// assert!(t.warnings.is_empty());
+///
+/// let t = from_rrule("RRULE:FREQ=DAILY;COUNT=10").unwrap();
+/// assert_eq!(t.value.schedule(), "0 0 * * *");
+/// assert_eq!(t.warnings.len(), 3);
+///
+/// assert!(from_rrule("FREQ=MONTHLY;BYDAY=-1FR").is_err());
+/// ```
+pub fn from_rrule(rule: &str) -> Result<Translation<Expression>, String> {
+    let rule = rule.trim();
+    let body = match rule.get(..6) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("RRULE:") => &rule[6..],
+        _ => rule,
+    };
+
+    let mut parts: HashMap<String, String> = HashMap::new();
+    for part in body.split(';').filter(|p| !p.is_empty()) {
+        let index = part
+            .find('=')
+            .ok_or_else(|| format!("Invalid part '{}': should be like 'KEY=VALUE'", part))?;
+        let key = part[..index].trim().to_uppercase();
+        let value = part[(index + 1)..].trim().to_uppercase();
+        if parts.insert(key.clone(), value).is_some() {
+            return Err(format!("{} appears more than once", key));
+        }
+    }
+
+    let freq = parts
+        .remove("FREQ")
+        .ok_or_else(|| "FREQ is required".to_string())?;
+    let level = FREQUENCIES.iter().position(|f| *f == freq).ok_or_else(|| {
+        format!(
+            "Unsupported FREQ '{}': should be one of {}",
+            freq,
+            FREQUENCIES.join(", ")
+        )
+    })?;
+
+    for key in ["BYSETPOS", "BYYEARDAY", "BYWEEKNO"].iter() {
+        if parts.contains_key(*key) {
+            return Err(format!("{} cannot be expressed in cron", key));
+        }
+    }
+    if let Some(seconds) = parts.remove("BYSECOND") {
+        if parse_list(&seconds, "BYSECOND")? != vec![0] {
+            return Err(format!("BYSECOND={} cannot be expressed in cron", seconds));
+        }
+    }
+    // WKST only matters with INTERVAL on WEEKLY or BYWEEKNO
+    parts.remove("WKST");
+
+    let mut warnings = vec![];
+    for key in ["COUNT", "UNTIL"].iter() {
+        if let Some(value) = parts.remove(*key) {
+            warnings.push(format!(
+                "{}={} is dropped: cron schedules have no end",
+                key, value
+            ));
+        }
+    }
+
+    let mut minute = numbers(parts.remove("BYMINUTE"), "BYMINUTE")?;
+    let mut hour = numbers(parts.remove("BYHOUR"), "BYHOUR")?;
+    if let Some(v) = parts.get("BYMONTHDAY") {
+        if v.contains('-') {
+            return Err(format!(
+                "BYMONTHDAY={} cannot be expressed in cron: negative days count from the end of the month",
+                v
+            ));
+        }
+    }
+    let mut date = numbers(parts.remove("BYMONTHDAY"), "BYMONTHDAY")?;
+    let mut month = numbers(parts.remove("BYMONTH"), "BYMONTH")?;
+    let mut day = parts
+        .remove("BYDAY")
+        .map(|v| parse_weekdays(&v))
+        .transpose()?;
+
+    let interval = match parts.remove("INTERVAL") {
+        Some(v) => v
+            .parse::<u32>()
+            .ok()
+            .filter(|i| *i > 0)
+            .ok_or_else(|| format!("Invalid INTERVAL '{}'", v))?,
+        None => 1,
+    };
+
+    if let Some(key) = parts.keys().next() {
+        return Err(format!("Unknown part '{}'", key));
+    }
+
+    // Parts below the frequency default to DTSTART, which is unknown here
+    let mut assume = |field: &mut Option<String>, key: &str, value: &str| {
+        if field.is_none() {
+            warnings.push(format!(
+                "{} is taken from DTSTART, which is unknown here; {} is assumed",
+                key, value
+            ));
+            *field = Some(value.to_string());
+        }
+    };
+    if level > 0 {
+        assume(&mut minute, "BYMINUTE", "0");
+    }
+    if level > 1 {
+        assume(&mut hour, "BYHOUR", "0");
+    }
+    match FREQUENCIES[level] {
+        "WEEKLY" => assume(&mut day, "BYDAY", "1"),
+        "MONTHLY" if date.is_none() && day.is_none() => assume(&mut date, "BYMONTHDAY", "1"),
+        "YEARLY" if date.is_none() && day.is_none() => {
+            if month.is_none() {
+                assume(&mut month, "BYMONTH", "1");
+            }
+            assume(&mut date, "BYMONTHDAY", "1");
+        }
+        _ => {}
+    }
+
+    if interval > 1 {
+        let (field, range, period) = match FREQUENCIES[level] {
+            "MINUTELY" => (&mut minute, "0-59", "hour"),
+            "HOURLY" => (&mut hour, "0-23", "day"),
+            "DAILY" => (&mut date, "1-31", "month"),
+            "MONTHLY" => (&mut month, "1-12", "year"),
+            f => {
+                return Err(format!(
+                    "INTERVAL={} cannot be expressed in cron with FREQ={}",
+                    interval, f
+                ))
+            }
+        };
+        if field.is_some() {
+            return Err(format!(
+                "INTERVAL={} cannot be expressed in cron together with a BY* part of FREQ={}",
+                interval, freq
+            ));
+        }
+        *field = Some(format!("{}/{}", range, interval));
+        warnings.push(format!(
+            "INTERVAL={} restarts at the start of every {} in cron, not at DTSTART",
+            interval, period
+        ));
+    }
+
+    let field = |v: &Option<String>| v.clone().unwrap_or_else(|| "*".to_string());
+    let text = format!(
+        "{} {} {} {} {}",
+        field(&minute),
+        field(&hour),
+        field(&date),
+        field(&month),
+        field(&day)
+    );
+    let e = Expression::new(&text)?;
+    Ok(Translation {
+        value: CompactExpression::from(&e).into(),
+        warnings,
+    })
+}
+
+/// Returns the comma separated numbers normalized for cron
+fn numbers(value: Option<String>, key: &str) -> Result<Option<String>, String> {
+    value
+        .map(|v| parse_list(&v, key).map(|n| join(&n)))
+        .transpose()
+}
+
+fn parse_list(value: &str, key: &str) -> Result<Vec<u32>, String> {
+    value
+        .split(',')
+        .map(|v| {
+            v.parse::<u32>()
+                .map_err(|_| format!("Invalid {} '{}'", key, value))
+        })
+        .collect()
+}
+
+/// Returns weekdays in cron like '1,3'
+fn parse_weekdays(value: &str) -> Result<String, String> {
+    let mut days = vec![];
+    for day in value.split(',') {
+        let index = WEEKDAYS.iter().position(|w| *w == day).ok_or_else(|| {
+            if day.len() > 2 && WEEKDAYS.contains(&&day[(day.len() - 2)..]) {
+                format!(
+                    "BYDAY={} cannot be expressed in cron: ordinal weekdays like '{}' are not supported",
+                    value, day
+                )
+            } else {
+                format!("Invalid BYDAY '{}'", day)
+            }
+        })?;
+        days.push(index as u32);
+    }
+    Ok(join(&days))
+}
+
+fn join(values: &[u32]) -> String {
+    values
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(text: &str) -> String {
+        to_rrule(&Expression::new(text).unwrap()).unwrap()
+    }
+
+    fn schedule(rule: &str) -> (String, usize) {
+        let t = from_rrule(rule).unwrap();
+        (t.value.schedule(), t.warnings.len())
+    }
+
+    #[test]
+    fn test_to_rrule() {
+        assert_eq!(rule("* * * * *"), "FREQ=MINUTELY");
+        assert_eq!(rule("0 9,18 * * *"), "FREQ=DAILY;BYHOUR=9,18;BYMINUTE=0");
+        assert_eq!(
+            rule("0 * * * 0,7"),
+            "FREQ=WEEKLY;BYDAY=SU;BYHOUR=0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23;BYMINUTE=0"
+        );
+        assert_eq!(
+            rule("0 0 13 * 5"),
+            "FREQ=MONTHLY;BYMONTHDAY=13;BYDAY=FR;BYHOUR=0;BYMINUTE=0"
+        );
+        assert_eq!(
+            rule("0 0 * 1 *"),
+            "FREQ=YEARLY;BYMONTH=1;BYMONTHDAY=1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31;BYHOUR=0;BYMINUTE=0"
+        );
+        assert_eq!(
+            rule("0 0 * 1 1"),
+            "FREQ=YEARLY;BYMONTH=1;BYDAY=MO;BYHOUR=0;BYMINUTE=0"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for text in [
+            "* * * * *",
+            "*/5 * * * *",
+            "0 9 * * 1-5",
+            "0 * * * 0",
+            "30 9 1,15 * *",
+            "0 0 13 * 5",
+            "0 0 * 1 *",
+            "15 3 29 2 *",
+        ]
+        .iter()
+        {
+            let e = Expression::new(text).unwrap();
+            let t = from_rrule(&to_rrule(&e).unwrap()).unwrap();
+            assert_eq!(
+                CompactExpression::from(&t.value),
+                CompactExpression::from(&e),
+                "{}",
+                text
+            );
+            assert!(t.warnings.is_empty(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_from_rrule_dtstart() {
+        assert_eq!(schedule("FREQ=HOURLY"), ("0 * * * *".to_string(), 1));
+        assert_eq!(
+            schedule("FREQ=WEEKLY;BYHOUR=9;BYMINUTE=30"),
+            ("30 9 * * 1".to_string(), 1)
+        );
+        assert_eq!(
+            schedule("FREQ=MONTHLY;BYHOUR=9;BYMINUTE=30"),
+            ("30 9 1 * *".to_string(), 1)
+        );
+        assert_eq!(
+            schedule("FREQ=YEARLY;BYHOUR=9;BYMINUTE=30"),
+            ("30 9 1 1 *".to_string(), 2)
+        );
+        assert_eq!(
+            schedule("FREQ=YEARLY;BYMONTH=3;BYHOUR=9;BYMINUTE=30"),
+            ("30 9 1 3 *".to_string(), 1)
+        );
+        assert_eq!(
+            schedule("FREQ=YEARLY;BYDAY=SA,SU;BYHOUR=9;BYMINUTE=30"),
+            ("30 9 * * 0,6".to_string(), 0)
+        );
+    }
+
+    #[test]
+    fn test_from_rrule_interval() {
+        assert_eq!(
+            schedule("FREQ=MINUTELY;INTERVAL=15"),
+            ("0,15,30,45 * * * *".to_string(), 1)
+        );
+        assert_eq!(
+            schedule("FREQ=HOURLY;INTERVAL=6;BYMINUTE=5"),
+            ("5 0,6,12,18 * * *".to_string(), 1)
+        );
+        assert_eq!(
+            schedule("FREQ=MONTHLY;INTERVAL=3;BYMONTHDAY=1;BYHOUR=0;BYMINUTE=0"),
+            ("0 0 1 1,4,7,10 *".to_string(), 1)
+        );
+        assert!(from_rrule("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO").is_err());
+        assert!(from_rrule("FREQ=MINUTELY;INTERVAL=15;BYMINUTE=0").is_err());
+        assert!(from_rrule("FREQ=DAILY;INTERVAL=0").is_err());
+    }
+
+    #[test]
+    fn test_from_rrule_error() {
+        assert!(from_rrule("").is_err());
+        assert!(from_rrule("FREQ=SECONDLY").is_err());
+        assert!(from_rrule("FREQ=DAILY;FREQ=DAILY").is_err());
+        assert!(from_rrule("FREQ=DAILY;BYHOUR=24").is_err());
+        assert!(from_rrule("FREQ=DAILY;BYHOUR=a").is_err());
+        assert!(from_rrule("FREQ=DAILY;BYSECOND=30").is_err());
+        assert!(from_rrule("FREQ=MONTHLY;BYMONTHDAY=-1").is_err());
+        assert!(from_rrule("FREQ=MONTHLY;BYDAY=1MO").is_err());
+        assert!(from_rrule("FREQ=YEARLY;BYWEEKNO=20").is_err());
+        assert!(from_rrule("FREQ=DAILY;FOO=1").is_err());
+        assert_eq!(
+            schedule("freq=daily;byhour=9;byminute=0;bysecond=0;wkst=su"),
+            ("0 9 * * *".to_string(), 0)
+        );
+    }
+}
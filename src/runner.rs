@@ -0,0 +1,338 @@
+//! A small user-space cron running the jobs of a crontab
+//!
+//! Jobs due at the same minute run concurrently, each in its own thread,
+//! so that a long job does not delay the next ones. A job still running
+//! when it is due again is started again alongside it.
+//!
+//! The next run is computed from the current time after each sleep, so that
+//! jobs missed while the machine sleeps or the clock jumps run once, like
+//! `Scheduler::tick`, instead of once per missed datetime.
+
+use crate::clock::Clock;
+use crate::crontab::{Crontab, Entry};
+use chrono::{DateTime, Duration, Local};
+use std::process::Command;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Options {
+    /// Shell running commands with `-c`, `SHELL` of the crontab if `None`
+    pub shell: Option<String>,
+}
+
+/// Result of a job
+#[derive(Debug, PartialEq, Clone)]
+pub struct Outcome {
+    /// 1-based line number of the job in the crontab
+    pub line: usize,
+    pub command: String,
+    /// Scheduled datetime
+    pub datetime: DateTime<Local>,
+    /// Exit code, `None` if killed by a signal or it cannot be spawned
+    pub status: Option<i32>,
+    pub stdout: String,
+    /// Standard error, or the reason why the command cannot be spawned
+    pub stderr: String,
+    pub elapsed: Duration,
+}
+
+impl Outcome {
+    /// Returns true if the command exits with 0
+    pub fn is_success(&self) -> bool {
+        self.status == Some(0)
+    }
+
+    /// Returns a line to log like `2019/05/28 09:00 line 3 exit 0 in 12ms: backup.sh`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::{Duration, Local, TimeZone};
+    /// use cron_gate::runner::Outcome;
+    ///
+    /// let outcome = Outcome {
+    ///     line: 3,
+    ///     command: "backup.sh".to_string(),
+    ///     datetime: Local.ymd(2019, 5, 28).and_hms(9, 0, 0),
+    ///     status: Some(0),
+    ///     stdout: String::new(),
+    ///     stderr: String::new(),
+    ///     elapsed: Duration::milliseconds(12),
+    /// };
+    /// assert_eq!(outcome.summary(), "2019/05/28 09:00 line 3 exit 0 in 12ms: backup.sh");
+    /// ```
+    pub fn summary(&self) -> String {
+        let status = match self.status {
+            Some(code) => format!("exit {}", code),
+            None => "failed".to_string(),
+        };
+        format!(
+            "{} line {} {} in {}ms: {}",
+            self.datetime.format("%Y/%m/%d %H:%M"),
+            self.line,
+            status,
+            self.elapsed.num_milliseconds(),
+            self.command
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Runner {
+    jobs: Vec<Entry>,
+    variables: Vec<(String, String)>,
+    shell: String,
+}
+
+impl Runner {
+    /// Returns a Runner of the jobs which fire in the crontab
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cron_gate::crontab::Crontab;
+    /// use cron_gate::runner::{Options, Runner};
+    ///
+    /// let crontab = Crontab::parse("SHELL=/bin/bash\n0 9 * * * a\n0 0 30 2 * b\n");
+    /// let runner = Runner::new(&crontab, &Options::default());
+    /// assert_eq!(runner.jobs().len(), 1);
+    /// assert_eq!(runner.shell(), "/bin/bash");
+    /// ```
+    pub fn new(crontab: &Crontab, options: &Options) -> Runner {
+        let shell = options
+            .shell
+            .clone()
+            .or_else(|| {
+                crontab
+                    .variables
+                    .iter()
+                    .rev()
+                    .find(|(name, _)| name == "SHELL")
+                    .map(|(_, value)| value.clone())
+            })
+            .unwrap_or_else(|| "/bin/sh".to_string());
+        Runner {
+            jobs: crontab
+                .entries
+                .iter()
+                .filter(|e| e.expression.is_satisfiable())
+                .cloned()
+                .collect(),
+            variables: crontab.variables.clone(),
+            shell,
+        }
+    }
+
+    pub fn jobs(&self) -> &[Entry] {
+        &self.jobs
+    }
+
+    pub fn shell(&self) -> &str {
+        &self.shell
+    }
+
+    /// Returns the earliest datetime from `from` and the jobs due then
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::{Local, TimeZone};
+    /// use cron_gate::crontab::Crontab;
+    /// use cron_gate::runner::{Options, Runner};
+    ///
+    /// let crontab = Crontab::parse("0 9 * * * a\n0 10 * * * b\n0 9 * * * c\n");
+    /// let runner = Runner::new(&crontab, &Options::default());
+    /// let (datetime, jobs) = runner.next_run(Local.ymd(2019, 5, 28).and_hms(9, 30, 0)).unwrap();
+    /// assert_eq!(datetime, Local.ymd(2019, 5, 28).and_hms(10, 0, 0));
+    /// assert_eq!(jobs[0].expression.command, "b");
+    /// ```
+    pub fn next_run(&self, from: DateTime<Local>) -> Option<(DateTime<Local>, Vec<&Entry>)> {
+        let nexts: Vec<(DateTime<Local>, &Entry)> = self
+            .jobs
+            .iter()
+            .filter_map(|job| {
+                job.expression
+                    .earler_excuting_datetimes(from, 1)
+                    .into_iter()
+                    .next()
+                    .map(|datetime| (datetime, job))
+            })
+            .collect();
+        let earliest = nexts.iter().map(|(datetime, _)| *datetime).min()?;
+        let jobs = nexts
+            .into_iter()
+            .filter(|(datetime, _)| *datetime == earliest)
+            .map(|(_, job)| job)
+            .collect();
+        Some((earliest, jobs))
+    }
+
+    /// Runs the command of the job through the shell with the variables of the crontab
+    /// and waits for it
+    pub fn execute(&self, job: &Entry, datetime: DateTime<Local>) -> Outcome {
+        let started = Instant::now();
+        let output = Command::new(&self.shell)
+            .arg("-c")
+            .arg(&job.expression.command)
+            .envs(self.variables.iter().map(|(k, v)| (k, v)))
+            .output();
+        let (status, stdout, stderr) = match output {
+            Ok(output) => (
+                output.status.code(),
+                String::from_utf8_lossy(&output.stdout).to_string(),
+                String::from_utf8_lossy(&output.stderr).to_string(),
+            ),
+            Err(e) => (
+                None,
+                String::new(),
+                format!("Cannot spawn {}: {}", self.shell, e),
+            ),
+        };
+        Outcome {
+            line: job.line,
+            command: job.expression.command.clone(),
+            datetime,
+            status,
+            stdout,
+            stderr,
+            elapsed: Duration::from_std(started.elapsed()).unwrap_or_else(|_| Duration::zero()),
+        }
+    }
+
+    /// Sleeps on the clock until each next run and executes the jobs due,
+    /// passing the outcomes to `log` from the threads of the jobs
+    ///
+    /// It runs forever unless no job fires, and then waits for the running jobs.
+    pub fn run<C, F>(&self, clock: &C, log: F) -> Result<(), String>
+    where
+        C: Clock + ?Sized,
+        F: Fn(&Outcome) + Send + Sync + 'static,
+    {
+        let log = Arc::new(log);
+        let mut threads = vec![];
+        let mut from = clock.now() + Duration::minutes(1);
+        let result = loop {
+            match self.run_next(clock, from, &log, &mut threads) {
+                Ok(next) => from = next,
+                Err(e) => break Err(e),
+            }
+        };
+        for thread in threads {
+            let _ = thread.join();
+        }
+        result
+    }
+
+    /// Sleeps until the next run from `from`, spawns the jobs due
+    /// and returns the datetime to look for the run after it
+    fn run_next<C, F>(
+        &self,
+        clock: &C,
+        from: DateTime<Local>,
+        log: &Arc<F>,
+        threads: &mut Vec<JoinHandle<()>>,
+    ) -> Result<DateTime<Local>, String>
+    where
+        C: Clock + ?Sized,
+        F: Fn(&Outcome) + Send + Sync + 'static,
+    {
+        let (datetime, _) = self
+            .next_run(from)
+            .ok_or_else(|| "No job fires".to_string())?;
+        clock.sleep_until(datetime);
+        let now = clock.now().max(datetime);
+        threads.retain(|thread| !thread.is_finished());
+        for job in self.jobs.iter() {
+            // Each job due by now runs once at its first datetime missed
+            let datetime = match job.expression.earler_excuting_datetimes(from, 1).pop() {
+                Some(datetime) if datetime <= now => datetime,
+                _ => continue,
+            };
+            let runner = self.clone();
+            let job = job.clone();
+            let log = log.clone();
+            threads.push(thread::spawn(move || log(&runner.execute(&job, datetime))));
+        }
+        Ok(now + Duration::minutes(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{FixedClock, ManualClock};
+    use chrono::TimeZone;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_next_run() {
+        let crontab = Crontab::parse("*/30 * * * * a\n0 * * * * b\n");
+        let runner = Runner::new(&crontab, &Options::default());
+        let (datetime, jobs) = runner
+            .next_run(Local.ymd(2019, 5, 28).and_hms(9, 1, 0))
+            .unwrap();
+        assert_eq!(datetime, Local.ymd(2019, 5, 28).and_hms(9, 30, 0));
+        assert_eq!(jobs.len(), 1);
+        let (_, jobs) = runner
+            .next_run(Local.ymd(2019, 5, 28).and_hms(9, 31, 0))
+            .unwrap();
+        assert_eq!(jobs.len(), 2);
+
+        let runner = Runner::new(&Crontab::parse("0 0 30 2 * a\n"), &Options::default());
+        let clock = FixedClock(Local.ymd(2019, 5, 28).and_hms(9, 0, 0));
+        assert_eq!(runner.next_run(clock.now()), None);
+        assert!(runner.run(&clock, |_| {}).is_err());
+    }
+
+    #[test]
+    fn test_run_next() {
+        let crontab = Crontab::parse("* * * * * true\n30 9 * * * true\n");
+        let runner = Runner::new(&crontab, &Options::default());
+        let clock = ManualClock::new(Local.ymd(2019, 5, 28).and_hms(9, 0, 30));
+        let count = Arc::new(AtomicUsize::new(0));
+        let c = count.clone();
+        let log = Arc::new(move |_: &Outcome| {
+            c.fetch_add(1, Ordering::SeqCst);
+        });
+        let mut threads = vec![];
+
+        let from = Local.ymd(2019, 5, 28).and_hms(9, 1, 0);
+        let next = runner.run_next(&clock, from, &log, &mut threads).unwrap();
+        assert_eq!(next, Local.ymd(2019, 5, 28).and_hms(9, 2, 0));
+
+        // Jobs missed by a suspend run once each
+        clock.set(Local.ymd(2019, 5, 28).and_hms(10, 0, 0));
+        let next = runner.run_next(&clock, next, &log, &mut threads).unwrap();
+        assert_eq!(next, Local.ymd(2019, 5, 28).and_hms(10, 1, 0));
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_execute() {
+        let crontab =
+            Crontab::parse("GREETING=hello\n* * * * * echo $GREETING; echo oops >&2; exit 3\n");
+        let runner = Runner::new(&crontab, &Options::default());
+        let datetime = Local.ymd(2019, 5, 28).and_hms(9, 0, 0);
+        let outcome = runner.execute(&runner.jobs()[0], datetime);
+        assert_eq!(outcome.line, 2);
+        assert_eq!(outcome.status, Some(3));
+        assert_eq!(outcome.stdout, "hello\n");
+        assert_eq!(outcome.stderr, "oops\n");
+        assert!(!outcome.is_success());
+
+        let options = Options {
+            shell: Some("/nonexistent/shell".to_string()),
+        };
+        let outcome = Runner::new(&crontab, &options).execute(&runner.jobs()[0], datetime);
+        assert_eq!(outcome.status, None);
+        assert!(outcome
+            .stderr
+            .starts_with("Cannot spawn /nonexistent/shell"));
+    }
+}
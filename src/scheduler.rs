@@ -0,0 +1,261 @@
+//! In-process scheduler running closures by cron expressions
+//!
+//! A job runs at most once per tick even if several of its datetimes have
+//! passed, e.g. after the machine sleeps, and its next datetime is computed
+//! from the current time again.
+
+use crate::clock::{Clock, SystemClock};
+use crate::expression::Expression;
+use chrono::{DateTime, Duration, Local};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Cancels the job it is returned for by `Scheduler::schedule`
+#[derive(Debug, Clone)]
+pub struct Handle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Handle {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+struct Job {
+    expression: Expression,
+    task: Box<dyn FnMut() + Send>,
+    next: DateTime<Local>,
+    handle: Handle,
+}
+
+pub struct Scheduler<C: Clock = SystemClock> {
+    clock: C,
+    jobs: Vec<Job>,
+}
+
+impl Scheduler<SystemClock> {
+    pub fn new() -> Scheduler<SystemClock> {
+        Scheduler::with_clock(SystemClock)
+    }
+}
+
+impl Default for Scheduler<SystemClock> {
+    fn default() -> Scheduler<SystemClock> {
+        Scheduler::new()
+    }
+}
+
+impl<C: Clock> Scheduler<C> {
+    pub fn with_clock(clock: C) -> Scheduler<C> {
+        Scheduler {
+            clock,
+            jobs: vec![],
+        }
+    }
+
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    /// Registers the task to run at the datetimes of the expression after now
+    ///
+    /// The command of the expression is ignored.
+    /// It returns an error if the expression is invalid or never fires.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::{Local, TimeZone};
+    /// use cron_gate::clock::ManualClock;
+    /// use cron_gate::scheduler::Scheduler;
+    ///
+    /// let clock = ManualClock::new(Local.ymd(2019, 5, 28).and_hms(9, 1, 30));
+    /// let mut scheduler = Scheduler::with_clock(clock);
+    /// scheduler.schedule("*/5 * * * *", || println!("Yo")).unwrap();
+    /// assert_eq!(scheduler.next_fire(), Some(Local.ymd(2019, 5, 28).and_hms(9, 5, 0)));
+    /// assert!(scheduler.schedule("0 0 30 2 *", || {}).is_err());
+    /// ```
+    pub fn schedule<F>(&mut self, expression: &str, task: F) -> Result<Handle, String>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        let expression = Expression::new(expression)?;
+        let next = next_after(&expression, self.clock.now())?;
+        let handle = Handle {
+            cancelled: Arc::new(AtomicBool::new(false)),
+        };
+        self.jobs.push(Job {
+            expression,
+            task: Box::new(task),
+            next,
+            handle: handle.clone(),
+        });
+        Ok(handle)
+    }
+
+    /// Returns the earliest datetime of the jobs not cancelled
+    pub fn next_fire(&self) -> Option<DateTime<Local>> {
+        self.jobs
+            .iter()
+            .filter(|job| !job.handle.is_cancelled())
+            .map(|job| job.next)
+            .min()
+    }
+
+    /// Returns the number of the jobs not cancelled
+    pub fn len(&self) -> usize {
+        self.jobs
+            .iter()
+            .filter(|job| !job.handle.is_cancelled())
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Runs the jobs due by now and returns how many ran
+    ///
+    /// Call it regularly, at least once a minute, to drive the jobs in an
+    /// event loop of your own instead of `run`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::{Duration, Local, TimeZone};
+    /// use cron_gate::clock::ManualClock;
+    /// use cron_gate::scheduler::Scheduler;
+    ///
+    /// let clock = ManualClock::new(Local.ymd(2019, 5, 28).and_hms(9, 0, 0));
+    /// let mut scheduler = Scheduler::with_clock(clock);
+    /// scheduler.schedule("0 * * * *", || {}).unwrap();
+    /// assert_eq!(scheduler.tick(), 0);
+    /// scheduler.clock().advance(Duration::hours(1));
+    /// assert_eq!(scheduler.tick(), 1);
+    /// assert_eq!(scheduler.tick(), 0);
+    /// ```
+    pub fn tick(&mut self) -> usize {
+        self.jobs.retain(|job| !job.handle.is_cancelled());
+        let now = self.clock.now();
+        let mut count = 0;
+        for job in self.jobs.iter_mut() {
+            if now < job.next {
+                continue;
+            }
+            (job.task)();
+            count += 1;
+            // Expressions which fire are checked in `schedule`
+            if let Ok(next) = next_after(&job.expression, now) {
+                job.next = next;
+            }
+        }
+        count
+    }
+
+    /// Sleeps until each next datetime and runs the jobs due
+    /// until all the jobs are cancelled
+    pub fn run(&mut self) {
+        while let Some(next) = self.next_fire() {
+            self.clock.sleep_until(next);
+            self.tick();
+        }
+    }
+}
+
+/// Returns the first datetime of the expression in a minute after the datetime
+pub(crate) fn next_after(
+    expression: &Expression,
+    datetime: DateTime<Local>,
+) -> Result<DateTime<Local>, String> {
+    if let Some(reason) = expression.never_fires_reason() {
+        return Err(reason);
+    }
+    expression
+        .earler_excuting_datetimes(datetime + Duration::minutes(1), 1)
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("No datetime after {}", datetime))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use chrono::TimeZone;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_tick() {
+        let clock = ManualClock::new(Local.ymd(2019, 5, 28).and_hms(8, 59, 59));
+        let mut scheduler = Scheduler::with_clock(clock);
+        let count = Arc::new(AtomicUsize::new(0));
+        let c = count.clone();
+        let handle = scheduler
+            .schedule("*/30 * * * *", move || {
+                c.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+        scheduler.schedule("0 10 * * *", || {}).unwrap();
+        assert_eq!(scheduler.len(), 2);
+        assert_eq!(
+            scheduler.next_fire(),
+            Some(Local.ymd(2019, 5, 28).and_hms(9, 0, 0))
+        );
+
+        scheduler
+            .clock()
+            .set(Local.ymd(2019, 5, 28).and_hms(9, 0, 0));
+        assert_eq!(scheduler.tick(), 1);
+        // Missed datetimes run once and the next is after now
+        scheduler
+            .clock()
+            .set(Local.ymd(2019, 5, 28).and_hms(10, 15, 0));
+        assert_eq!(scheduler.tick(), 2);
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            scheduler.next_fire(),
+            Some(Local.ymd(2019, 5, 28).and_hms(10, 30, 0))
+        );
+
+        handle.cancel();
+        assert_eq!(scheduler.len(), 1);
+        assert_eq!(
+            scheduler.next_fire(),
+            Some(Local.ymd(2019, 5, 29).and_hms(10, 0, 0))
+        );
+    }
+
+    #[test]
+    fn test_run() {
+        let clock = ManualClock::new(Local.ymd(2019, 5, 28).and_hms(9, 0, 0));
+        let mut scheduler = Scheduler::with_clock(clock);
+        let fired = Arc::new(Mutex::new(vec![]));
+        let handle = Arc::new(Mutex::new(None::<Handle>));
+        let (f, h) = (fired.clone(), handle.clone());
+        *handle.lock().unwrap() = Some(
+            scheduler
+                .schedule("0 9 * * 1-5", move || {
+                    let mut fired = f.lock().unwrap();
+                    fired.push(());
+                    if fired.len() == 3 {
+                        h.lock().unwrap().as_ref().unwrap().cancel();
+                    }
+                })
+                .unwrap(),
+        );
+        scheduler.run();
+        assert_eq!(fired.lock().unwrap().len(), 3);
+        // Wednesday, Thursday and Friday
+        assert_eq!(
+            scheduler.clock().now(),
+            Local.ymd(2019, 5, 31).and_hms(9, 0, 0)
+        );
+        assert!(scheduler.is_empty());
+    }
+}
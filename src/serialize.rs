@@ -0,0 +1,278 @@
+//! Serde support, enabled by the `serde` feature
+//!
+//! `Expression` is serialized as its alternate string `{:#}` and deserialized
+//! through `Expression::new`, so an invalid expression fails while loading.
+//! Values chosen for `~` are kept as `0~30=17`, so an expression built
+//! `with_seed` loads with the same values.
+//!
+//! ```
+//! use cron_gate::expression::Expression;
+//!
+//! let e: Expression = serde_json::from_str("\"0 9 * * 1-5 echo 'Yo'\"").unwrap();
+//! assert_eq!(e.hour_vec, vec![9]);
+//! assert_eq!(serde_json::to_string(&e).unwrap(), "\"0 9 * * 1-5 echo 'Yo'\"");
+//!
+//! let e = Expression::with_seed("0~30 9 * * * command", 42).unwrap();
+//! let json = serde_json::to_string(&e).unwrap();
+//! assert_eq!(json, format!("\"0~30={} 9 * * * command\"", e.minute_vec[0]));
+//! assert_eq!(serde_json::from_str::<Expression>(&json).unwrap(), e);
+//!
+//! assert!(serde_json::from_str::<Expression>("\"0 24 * * * command\"").is_err());
+//! assert!(serde_json::from_str::<Expression>("\"0~30=31 9 * * * command\"").is_err());
+//! ```
+
+use crate::expression::{parse_block, parse_block_with_chosen, Expression};
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+impl Serialize for Expression {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&format_args!("{:#}", self))
+    }
+}
+
+impl<'de> Deserialize<'de> for Expression {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Expression, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        parse_alternate(&text).map_err(de::Error::custom)
+    }
+}
+
+/// Parses the alternate form of `Expression` like `0~30=17 9 * * * command`
+fn parse_alternate(text: &str) -> Result<Expression, String> {
+    let mut fields: Vec<&str> = text.split_whitespace().collect();
+    let mut chosen = vec![];
+    for (index, field) in fields.iter_mut().take(5).enumerate() {
+        if let Some(at) = field.find('=') {
+            chosen.push((index, &field[(at + 1)..]));
+            *field = &field[..at];
+        }
+    }
+    let mut e = Expression::new(&fields.join(" "))?;
+    for (index, values) in chosen {
+        let (min, max) = RANGES[index];
+        let values = parse_block(values, min, max)?;
+        choose(&mut e, index, &values)?;
+    }
+    Ok(e)
+}
+
+/// Ranges of the five fields
+const RANGES: [(u32, u32); 5] = [(0, 59), (0, 23), (1, 31), (1, 12), (0, 7)];
+
+/// Replaces the values of the field at the index with the values chosen for its `~`
+fn choose(e: &mut Expression, index: usize, values: &[u32]) -> Result<(), String> {
+    let (min, max) = RANGES[index];
+    let (raw, vec) = match index {
+        0 => (&e.minute, &mut e.minute_vec),
+        1 => (&e.hour, &mut e.hour_vec),
+        2 => (&e.date, &mut e.date_vec),
+        3 => (&e.month, &mut e.month_vec),
+        _ => (&e.day, &mut e.day_vec),
+    };
+    *vec = parse_block_with_chosen(raw, min, max, values)?;
+    Ok(())
+}
+
+/// Expanded form of `Expression` including the `*_vec` fields
+///
+/// Use it with `#[serde(with = "cron_gate::serialize::expanded")]`.
+/// The `*_vec` fields may be omitted when deserializing,
+/// but they must agree with the raw fields if present.
+/// Those of fields with `~` are the values chosen for it.
+///
+/// # Examples
+///
+/// ```
+/// use cron_gate::expression::Expression;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Job {
+///     #[serde(with = "cron_gate::serialize::expanded")]
+///     schedule: Expression,
+/// }
+///
+/// let job = Job { schedule: Expression::new("*/20 9 * * * command").unwrap() };
+/// let json = serde_json::to_string(&job).unwrap();
+/// assert_eq!(
+///     json,
+///     r#"{"schedule":{"minute":"*/20","hour":"9","date":"*","month":"*","day":"*","command":"command","#.to_owned()
+///         + r#""minute_vec":[0,20,40],"hour_vec":[9],"date_vec":[1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31],"#
+///         + r#""month_vec":[1,2,3,4,5,6,7,8,9,10,11,12],"day_vec":[0,1,2,3,4,5,6,7]}}"#
+/// );
+/// let loaded: Job = serde_json::from_str(&json).unwrap();
+/// assert_eq!(loaded.schedule, job.schedule);
+/// ```
+pub mod expanded {
+    use crate::expression::Expression;
+    use serde::de;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize)]
+    struct ExpandedRef<'a> {
+        minute: &'a str,
+        hour: &'a str,
+        date: &'a str,
+        month: &'a str,
+        day: &'a str,
+        command: &'a str,
+        minute_vec: &'a [u32],
+        hour_vec: &'a [u32],
+        date_vec: &'a [u32],
+        month_vec: &'a [u32],
+        day_vec: &'a [u32],
+    }
+
+    #[derive(Deserialize)]
+    struct Expanded {
+        minute: String,
+        hour: String,
+        date: String,
+        month: String,
+        day: String,
+        command: String,
+        minute_vec: Option<Vec<u32>>,
+        hour_vec: Option<Vec<u32>>,
+        date_vec: Option<Vec<u32>>,
+        month_vec: Option<Vec<u32>>,
+        day_vec: Option<Vec<u32>>,
+    }
+
+    pub fn serialize<S: Serializer>(e: &Expression, serializer: S) -> Result<S::Ok, S::Error> {
+        ExpandedRef {
+            minute: &e.minute,
+            hour: &e.hour,
+            date: &e.date,
+            month: &e.month,
+            day: &e.day,
+            command: &e.command,
+            minute_vec: &e.minute_vec,
+            hour_vec: &e.hour_vec,
+            date_vec: &e.date_vec,
+            month_vec: &e.month_vec,
+            day_vec: &e.day_vec,
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Expression, D::Error> {
+        let x = Expanded::deserialize(deserializer)?;
+        let mut e = Expression::new(&format!(
+            "{} {} {} {} {} {}",
+            x.minute, x.hour, x.date, x.month, x.day, x.command
+        ))
+        .map_err(de::Error::custom)?;
+
+        let raws = [&x.minute, &x.hour, &x.date, &x.month, &x.day];
+        let givens = [
+            &x.minute_vec,
+            &x.hour_vec,
+            &x.date_vec,
+            &x.month_vec,
+            &x.day_vec,
+        ];
+        for (index, (raw, given)) in raws.iter().zip(givens.iter()).enumerate() {
+            if let (true, Some(given)) = (raw.contains('~'), given) {
+                super::choose(&mut e, index, given).map_err(de::Error::custom)?;
+            }
+        }
+
+        let vecs = [
+            ("minute_vec", &x.minute_vec, &e.minute_vec),
+            ("hour_vec", &x.hour_vec, &e.hour_vec),
+            ("date_vec", &x.date_vec, &e.date_vec),
+            ("month_vec", &x.month_vec, &e.month_vec),
+            ("day_vec", &x.day_vec, &e.day_vec),
+        ];
+        for (name, given, parsed) in vecs.iter() {
+            if let Some(given) = given {
+                if given != *parsed {
+                    return Err(de::Error::custom(format!(
+                        "{} {:?} does not match the expression: {:?}",
+                        name, given, parsed
+                    )));
+                }
+            }
+        }
+        Ok(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expression::{CronLine, Expression, DATE_FORMAT};
+    use chrono::offset::TimeZone;
+    use chrono::Local;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Job {
+        #[serde(with = "super::expanded")]
+        schedule: Expression,
+    }
+
+    #[test]
+    fn test_expression_without_command() {
+        let e: Expression = serde_json::from_str("\"0 9 * * *\"").unwrap();
+        assert_eq!(e.command, "[command]");
+        let json = serde_json::to_string(&e).unwrap();
+        assert_eq!(serde_json::from_str::<Expression>(&json).unwrap(), e);
+    }
+
+    #[test]
+    fn test_invalid_expression() {
+        let err = serde_json::from_str::<Expression>("\"0 0 32 * * command\"").unwrap_err();
+        assert!(err.to_string().starts_with("Error on date: '32'"));
+        assert!(serde_json::from_str::<Expression>("1").is_err());
+    }
+
+    #[test]
+    fn test_expanded_without_vecs() {
+        let json = r#"{"schedule":{"minute":"0","hour":"9","date":"*","month":"*","day":"1-5","command":"command"}}"#;
+        let job: Job = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            job.schedule,
+            Expression::new("0 9 * * 1-5 command").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_expanded_mismatch() {
+        let json = r#"{"schedule":{"minute":"0","hour":"9","date":"*","month":"*","day":"1-5","command":"command","hour_vec":[10]}}"#;
+        let err = serde_json::from_str::<Job>(json).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("hour_vec [10] does not match the expression: [9]"));
+    }
+
+    #[test]
+    fn test_seeded_round_trip() {
+        let e = Expression::with_seed("5,0~30 ~ * * * command", 7).unwrap();
+        let json = serde_json::to_string(&e).unwrap();
+        assert_eq!(serde_json::from_str::<Expression>(&json).unwrap(), e);
+
+        let job = Job { schedule: e };
+        let json = serde_json::to_string(&job).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Job>(&json).unwrap().schedule,
+            job.schedule
+        );
+
+        let json = r#"{"schedule":{"minute":"0~30","hour":"9","date":"*","month":"*","day":"*","command":"command","minute_vec":[40]}}"#;
+        let err = serde_json::from_str::<Job>(json).unwrap_err();
+        assert!(err.to_string().starts_with("'0~30' cannot choose [40]"));
+    }
+
+    #[test]
+    fn test_cron_line() {
+        let line = CronLine {
+            datetime: Local
+                .datetime_from_str("2019/5/28 9:0", DATE_FORMAT)
+                .unwrap(),
+            command: "command".to_string(),
+        };
+        let json = serde_json::to_string(&line).unwrap();
+        assert_eq!(serde_json::from_str::<CronLine>(&json).unwrap(), line);
+    }
+}
@@ -0,0 +1,162 @@
+//! Async stream of the datetimes an expression fires at
+//!
+//! The stream sleeps on the timer of Tokio in steps of at most a minute and
+//! compares the clock with the next datetime after each step, so that jumps
+//! of the wall clock, e.g. on resume or by NTP, do not accumulate drift.
+//! Datetimes missed by a jump forward are yielded once, like `Scheduler::tick`.
+
+use crate::clock::{Clock, SystemClock};
+use crate::expression::Expression;
+use crate::scheduler::next_after;
+use chrono::{DateTime, Local};
+use futures_core::Stream;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::time::{sleep, Sleep};
+
+/// The longest step to sleep before checking the clock again
+const MAX_SLEEP: std::time::Duration = std::time::Duration::from_secs(60);
+
+pub struct FireStream<C: Clock = SystemClock> {
+    expression: Expression,
+    clock: C,
+    next: Option<DateTime<Local>>,
+    /// Created on the first poll since it needs the runtime
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+impl FireStream<SystemClock> {
+    /// Returns a stream of the datetimes of the expression after now
+    ///
+    /// It returns an error if the expression never fires.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cron_gate::expression::Expression;
+    /// use cron_gate::stream::FireStream;
+    ///
+    /// let e = Expression::new("0 9 * * 1-5 command").unwrap();
+    /// assert!(FireStream::new(&e).is_ok());
+    /// let e = Expression::new("0 0 30 2 * command").unwrap();
+    /// assert!(FireStream::new(&e).is_err());
+    /// ```
+    pub fn new(expression: &Expression) -> Result<FireStream<SystemClock>, String> {
+        FireStream::with_clock(expression, SystemClock)
+    }
+}
+
+impl<C: Clock> FireStream<C> {
+    /// Returns a stream of the datetimes of the expression after now of the clock
+    pub fn with_clock(expression: &Expression, clock: C) -> Result<FireStream<C>, String> {
+        let next = next_after(expression, clock.now())?;
+        Ok(FireStream {
+            expression: expression.clone(),
+            clock,
+            next: Some(next),
+            sleep: None,
+        })
+    }
+
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+}
+
+impl<C: Clock + Unpin> Stream for FireStream<C> {
+    type Item = DateTime<Local>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            let next = match this.next {
+                Some(next) => next,
+                None => return Poll::Ready(None),
+            };
+            let now = this.clock.now();
+            if next <= now {
+                this.next = next_after(&this.expression, now).ok();
+                return Poll::Ready(Some(next));
+            }
+
+            let wait = (next - now).to_std().unwrap_or(MAX_SLEEP).min(MAX_SLEEP);
+            let deadline = tokio::time::Instant::now() + wait;
+            match this.sleep.as_mut() {
+                Some(s) => s.as_mut().reset(deadline),
+                None => this.sleep = Some(Box::pin(sleep(wait))),
+            }
+            if let Some(s) = this.sleep.as_mut() {
+                if s.as_mut().poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    use std::sync::Mutex;
+    use tokio::time::Instant;
+
+    /// Wall clock following the paused time of Tokio, which may jump by `offset`
+    struct TestClock {
+        origin: DateTime<Local>,
+        start: Instant,
+        offset: Mutex<Duration>,
+    }
+
+    impl TestClock {
+        fn new(origin: DateTime<Local>) -> TestClock {
+            TestClock {
+                origin,
+                start: Instant::now(),
+                offset: Mutex::new(Duration::zero()),
+            }
+        }
+
+        fn jump(&self, duration: Duration) {
+            let mut offset = self.offset.lock().unwrap();
+            *offset = *offset + duration;
+        }
+    }
+
+    impl Clock for TestClock {
+        fn now(&self) -> DateTime<Local> {
+            self.origin
+                + Duration::from_std(Instant::now() - self.start).unwrap()
+                + *self.offset.lock().unwrap()
+        }
+
+        /// Not used by the stream, which sleeps on the timer of Tokio
+        fn sleep_until(&self, _: DateTime<Local>) {}
+    }
+
+    async fn next<C: Clock + Unpin>(stream: &mut FireStream<C>) -> Option<DateTime<Local>> {
+        std::future::poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_poll_next() {
+        let e = Expression::new("*/5 * * * * command").unwrap();
+        let clock = TestClock::new(Local.ymd(2019, 5, 28).and_hms(9, 1, 30));
+        let mut stream = FireStream::with_clock(&e, clock).unwrap();
+        let at = |h, m| Local.ymd(2019, 5, 28).and_hms(h, m, 0);
+
+        assert_eq!(next(&mut stream).await, Some(at(9, 5)));
+        assert!(stream.clock().now() >= at(9, 5));
+
+        // A jump forward yields the missed datetime once
+        stream.clock().jump(Duration::hours(1));
+        assert_eq!(next(&mut stream).await, Some(at(9, 10)));
+        assert_eq!(next(&mut stream).await, Some(at(10, 10)));
+
+        // A jump backward waits for the wall clock
+        stream.clock().jump(Duration::hours(-1));
+        assert_eq!(next(&mut stream).await, Some(at(10, 15)));
+        assert!(stream.clock().now() >= at(10, 15));
+    }
+}
@@ -0,0 +1,435 @@
+//! Translation between cron expressions and systemd timers
+//!
+//! `OnCalendar=` has AND semantics for the weekday and the date as this crate,
+//! so every satisfiable expression has an equivalent spec.
+
+use crate::compact::{format_values, CompactExpression};
+use crate::expression::Expression;
+use crate::translation::Translation;
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const FULL_WEEKDAYS: [&str; 7] = [
+    "sunday",
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+];
+/// Weekdays of systemd start from Monday, so do ranges of them
+const MONDAY_FIRST: [u32; 7] = [1, 2, 3, 4, 5, 6, 0];
+
+/// A pair of a `.timer` unit and the `.service` unit activated by it
+///
+/// They should be installed with the same name like `backup.timer` and `backup.service`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Units {
+    pub timer: String,
+    pub service: String,
+}
+
+/// Returns the `OnCalendar=` spec equivalent to the schedule
+///
+/// It is an error if the expression never fires.
+///
+/// # Examples
+///
+/// ```
+/// use cron_gate::expression::Expression;
+/// use cron_gate::systemd::to_on_calendar;
+///
+/// let spec = |text| to_on_calendar(&Expression::new(text).unwrap());
+/// assert_eq!(spec("30 9 * * 1-5").unwrap(), "Mon..Fri *-*-* 09:30:00");
+/// assert_eq!(spec("*/15 * 1 1,7 *").unwrap(), "*-01,07-01 *:00,15,30,45:00");
+/// assert!(spec("0 0 31 4 *").is_err());
+/// ```
+pub fn to_on_calendar(expression: &Expression) -> Result<String, String> {
+    if let Some(reason) = expression.never_fires_reason() {
+        return Err(reason);
+    }
+
+    let c = CompactExpression::from(expression).to_expression("");
+    let weekdays: Vec<u32> = MONDAY_FIRST
+        .iter()
+        .cloned()
+        .filter(|d| c.day_vec.iter().any(|v| v % 7 == *d))
+        .collect();
+
+    let date = format!(
+        "*-{}-{}",
+        format_component(&c.month_vec, 1, 12),
+        format_component(&c.date_vec, 1, 31)
+    );
+    let time = format!(
+        "{}:{}:00",
+        format_component(&c.hour_vec, 0, 23),
+        format_component(&c.minute_vec, 0, 59)
+    );
+    if weekdays.len() == 7 {
+        Ok(format!("{} {}", date, time))
+    } else {
+        Ok(format!("{} {} {}", format_weekdays(&weekdays), date, time))
+    }
+}
+
+/// Returns the expression equivalent to the `OnCalendar=` spec with `[command]` as its command
+///
+/// Shorthands like `daily` and `weekly` are accepted.
+/// A year and a time zone are dropped with warnings.
+/// Seconds other than zero and the last days of months (`~`) are errors.
+///
+/// # Examples
+///
+/// ```
+/// use cron_gate::systemd::from_on_calendar;
+///
+/// let t = from_on_calendar("Mon..Fri *-*-* 09:30:00").unwrap();
+/// assert_eq!(t.value.schedule(), "30 9 * * 1-5");
+/// assert!(t.warnings.is_empty());
+///
+/// assert_eq!(from_on_calendar("weekly").unwrap().value.schedule(), "0 0 * * 1");
+/// assert_eq!(from_on_calendar("*:0/20").unwrap().value.schedule(), "0,20,40 * * * *");
+///
+/// let t = from_on_calendar("2030-*-01 12:00 UTC").unwrap();
+/// assert_eq!(t.value.schedule(), "0 12 1 * *");
+/// assert_eq!(t.warnings.len(), 2);
+///
+/// assert!(from_on_calendar("*-*-* 00:00:30").is_err());
+/// ```
+pub fn from_on_calendar(spec: &str) -> Result<Translation<Expression>, String> {
+    let spec = spec.trim();
+    let normalized = match spec.to_lowercase().as_str() {
+        "minutely" => "*-*-* *:*:00",
+        "hourly" => "*-*-* *:00:00",
+        "daily" => "*-*-* 00:00:00",
+        "weekly" => "Mon *-*-* 00:00:00",
+        "monthly" => "*-*-01 00:00:00",
+        "yearly" | "annually" => "*-01-01 00:00:00",
+        "quarterly" => "*-01,04,07,10-01 00:00:00",
+        "semiannually" => "*-01,07-01 00:00:00",
+        _ => spec,
+    };
+
+    let mut warnings = vec![];
+    let mut weekday = None;
+    let mut date = None;
+    let mut time = None;
+    let tokens: Vec<&str> = normalized.split_whitespace().collect();
+    if tokens.is_empty() {
+        return Err("OnCalendar spec is empty".to_string());
+    }
+    for (i, token) in tokens.iter().enumerate() {
+        let head = token.chars().next().unwrap_or(' ');
+        if token.contains(':') && time.is_none() {
+            time = Some(*token);
+        } else if (head.is_ascii_digit() || head == '*') && token.contains('-') && date.is_none() {
+            date = Some(*token);
+        } else if i == 0 && head.is_ascii_alphabetic() {
+            weekday = Some(*token);
+        } else if i == tokens.len() - 1 && head.is_ascii_alphabetic() {
+            warnings.push(format!(
+                "Time zone '{}' is dropped: cron runs in local time",
+                token
+            ));
+        } else {
+            return Err(format!("Invalid part '{}' of '{}'", token, spec));
+        }
+    }
+
+    let day = match weekday {
+        Some(w) => parse_weekdays(w)?,
+        None => "*".to_string(),
+    };
+
+    let (month, date) = match date {
+        Some(d) => {
+            if d.contains('~') {
+                return Err(format!(
+                    "Date '{}' cannot be expressed in cron: '~' counts from the end of the month",
+                    d
+                ));
+            }
+            let parts: Vec<&str> = d.split('-').collect();
+            let (month, date) = match parts.len() {
+                2 => (parts[0], parts[1]),
+                3 => {
+                    if parts[0] != "*" {
+                        warnings.push(format!("Year '{}' is dropped: cron has no year", parts[0]));
+                    }
+                    (parts[1], parts[2])
+                }
+                _ => return Err(format!("Invalid date '{}': should be like '*-*-01'", d)),
+            };
+            (parse_component(month, 12)?, parse_component(date, 31)?)
+        }
+        None => ("*".to_string(), "*".to_string()),
+    };
+
+    let (hour, minute) = match time {
+        Some(t) => {
+            let parts: Vec<&str> = t.split(':').collect();
+            if parts.len() == 3 && parse_component(parts[2], 59)?.parse::<u32>() != Ok(0) {
+                return Err(format!(
+                    "Seconds '{}' cannot be expressed in cron",
+                    parts[2]
+                ));
+            }
+            if parts.len() < 2 || parts.len() > 3 {
+                return Err(format!("Invalid time '{}': should be like '09:30:00'", t));
+            }
+            (
+                parse_component(parts[0], 23)?,
+                parse_component(parts[1], 59)?,
+            )
+        }
+        None => ("0".to_string(), "0".to_string()),
+    };
+
+    let e = Expression::new(&format!("{} {} {} {} {}", minute, hour, date, month, day))?;
+    Ok(Translation {
+        value: CompactExpression::from(&e).into(),
+        warnings,
+    })
+}
+
+/// Returns the timer and the service units running the command on the schedule
+///
+/// The variables are set by `Environment=` like those of a crontab.
+///
+/// # Examples
+///
+/// ```
+/// use cron_gate::expression::Expression;
+/// use cron_gate::systemd::units;
+///
+/// let e = Expression::new("0 3 * * * backup.sh \"$HOME\"").unwrap();
+/// let u = units(&e, &[("SHELL".to_string(), "/bin/bash".to_string())]).unwrap();
+/// assert!(u.timer.contains("\nOnCalendar=*-*-* 03:00:00\n"));
+/// assert!(u.service.contains("\nEnvironment=\"SHELL=/bin/bash\"\n"));
+/// assert!(u.service.contains("\nExecStart=/bin/sh -c \"backup.sh \\\"$$HOME\\\"\"\n"));
+/// ```
+pub fn units(expression: &Expression, variables: &[(String, String)]) -> Result<Units, String> {
+    let spec = to_on_calendar(expression)?;
+    let description = expression.command.replace('%', "%%");
+
+    let timer = format!(
+        "[Unit]\nDescription=Timer of {}\n\n[Timer]\nOnCalendar={}\nPersistent=true\n\n[Install]\nWantedBy=timers.target\n",
+        description, spec
+    );
+
+    let mut service = format!(
+        "[Unit]\nDescription={}\n\n[Service]\nType=oneshot\n",
+        description
+    );
+    for (name, value) in variables {
+        service.push_str(&format!(
+            "Environment=\"{}={}\"\n",
+            name,
+            escape_exec(value)
+        ));
+    }
+    service.push_str(&format!(
+        "ExecStart=/bin/sh -c \"{}\"\n",
+        escape_exec(&expression.command)
+    ));
+
+    Ok(Units { timer, service })
+}
+
+/// Returns a component like '09', '01..05' or '00,15,30,45'
+fn format_component(values: &[u32], min: u32, max: u32) -> String {
+    let field = format_values(values, min, max);
+    if field == "*" {
+        return field;
+    }
+    field
+        .split(',')
+        .map(|unit| {
+            unit.split('-')
+                .map(|v| format!("{:02}", v.parse::<u32>().unwrap_or(0)))
+                .collect::<Vec<String>>()
+                .join("..")
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Returns weekdays in Monday-first order like 'Mon..Fri' or 'Mon,Wed'
+fn format_weekdays(weekdays: &[u32]) -> String {
+    let positions: Vec<usize> = weekdays
+        .iter()
+        .map(|d| MONDAY_FIRST.iter().position(|m| m == d).unwrap_or(0))
+        .collect();
+    let mut units = vec![];
+    let mut i = 0;
+    while i < positions.len() {
+        let mut j = i;
+        while j + 1 < positions.len() && positions[j + 1] == positions[j] + 1 {
+            j += 1;
+        }
+        let name = |k: usize| WEEKDAYS[MONDAY_FIRST[positions[k]] as usize];
+        if j - i >= 2 {
+            units.push(format!("{}..{}", name(i), name(j)));
+        } else {
+            units.extend((i..=j).map(|k| name(k).to_string()));
+        }
+        i = j + 1;
+    }
+    units.join(",")
+}
+
+/// Returns the weekday of the name like 'Mon' or 'monday'
+fn parse_weekday(name: &str) -> Result<u32, String> {
+    let lower = name.to_lowercase();
+    FULL_WEEKDAYS
+        .iter()
+        .position(|w| *w == lower || (lower.len() == 3 && w.starts_with(&lower)))
+        .map(|i| i as u32)
+        .ok_or_else(|| format!("Invalid weekday '{}'", name))
+}
+
+/// Returns weekdays in cron like '1-5' from 'Mon..Fri'
+fn parse_weekdays(text: &str) -> Result<String, String> {
+    let mut units = vec![];
+    for unit in text.split(',') {
+        match unit.find("..") {
+            Some(index) => {
+                let from = parse_weekday(&unit[..index])?;
+                let to = parse_weekday(&unit[(index + 2)..])?;
+                let position = |d: u32| MONDAY_FIRST.iter().position(|m| *m == d).unwrap_or(0);
+                if position(from) > position(to) {
+                    return Err(format!(
+                        "Invalid weekdays '{}': ranges go from Monday to Sunday",
+                        unit
+                    ));
+                }
+                for d in MONDAY_FIRST[position(from)..=position(to)].iter() {
+                    units.push(d.to_string());
+                }
+            }
+            None => units.push(parse_weekday(unit)?.to_string()),
+        }
+    }
+    Ok(units.join(","))
+}
+
+/// Returns a cron field from a component like '01..05', '0/20' or '*'
+fn parse_component(text: &str, max: u32) -> Result<String, String> {
+    let mut units = vec![];
+    for unit in text.split(',') {
+        let (body, step) = match unit.find('/') {
+            Some(index) => (&unit[..index], Some(&unit[(index + 1)..])),
+            None => (unit, None),
+        };
+        let number = |v: &str| {
+            v.parse::<u32>()
+                .map(|n| n.to_string())
+                .map_err(|_| format!("Invalid value '{}' of '{}'", v, text))
+        };
+        let range = match body.find("..") {
+            Some(index) => format!(
+                "{}-{}",
+                number(&body[..index])?,
+                number(&body[(index + 2)..])?
+            ),
+            None if body == "*" => "*".to_string(),
+            // 'a/b' repeats from 'a' until the end
+            None if step.is_some() => format!("{}-{}", number(body)?, max),
+            None => number(body)?,
+        };
+        match step {
+            Some(s) => units.push(format!("{}/{}", range, number(s)?)),
+            None => units.push(range),
+        }
+    }
+    Ok(units.join(","))
+}
+
+/// Escapes a value in double quotes of ExecStart= and Environment=
+fn escape_exec(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('%', "%%")
+        .replace('$', "$$")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(text: &str) -> String {
+        to_on_calendar(&Expression::new(text).unwrap()).unwrap()
+    }
+
+    fn schedule(spec: &str) -> String {
+        from_on_calendar(spec).unwrap().value.schedule()
+    }
+
+    #[test]
+    fn test_to_on_calendar() {
+        assert_eq!(spec("* * * * *"), "*-*-* *:*:00");
+        assert_eq!(spec("0 0 * * 0,7"), "Sun *-*-* 00:00:00");
+        assert_eq!(spec("0 0 * * 0,1,2,6"), "Mon,Tue,Sat,Sun *-*-* 00:00:00");
+        assert_eq!(spec("0 0 * * 5-7"), "Fri..Sun *-*-* 00:00:00");
+        assert_eq!(spec("0 8-18 1-5 * *"), "*-*-01..05 08..18:00:00");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for text in [
+            "* * * * *",
+            "*/5 * * * *",
+            "30 9 * * 1-5",
+            "0 0 * * 0",
+            "0 0 13 * 5",
+            "15 3 29 2 *",
+            "0 8-18 1-5 * 1,3,6",
+        ]
+        .iter()
+        {
+            let e = Expression::new(text).unwrap();
+            let t = from_on_calendar(&to_on_calendar(&e).unwrap()).unwrap();
+            assert_eq!(
+                CompactExpression::from(&t.value),
+                CompactExpression::from(&e),
+                "{}",
+                text
+            );
+            assert!(t.warnings.is_empty(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_from_on_calendar() {
+        assert_eq!(schedule("hourly"), "0 * * * *");
+        assert_eq!(schedule("Quarterly"), "0 0 1 1,4,7,10 *");
+        assert_eq!(schedule("Sat,Sun 10:00"), "0 10 * * 0,6");
+        assert_eq!(schedule("Saturday *-12-25"), "0 0 25 12 6");
+        assert_eq!(schedule("*-*-1/10 6:00:00"), "0 6 1,11,21,31 * *");
+        assert_eq!(schedule("12-25 *:*"), "* * 25 12 *");
+        assert_eq!(
+            from_on_calendar("Mon 09:00 Asia/Tokyo").unwrap().warnings,
+            vec!["Time zone 'Asia/Tokyo' is dropped: cron runs in local time".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_from_on_calendar_error() {
+        assert!(from_on_calendar("").is_err());
+        assert!(from_on_calendar("*-02~01 00:00").is_err());
+        assert!(from_on_calendar("*:*:*").is_err());
+        assert!(from_on_calendar("Sun..Mon 00:00").is_err());
+        assert!(from_on_calendar("Funday 00:00").is_err());
+        assert!(from_on_calendar("*-*-* 25:00").is_err());
+        assert!(from_on_calendar("*-*-* 00:00 UTC extra").is_err());
+    }
+
+    #[test]
+    fn test_escape_exec() {
+        assert_eq!(
+            escape_exec("date +%Y \"$x\" \\"),
+            "date +%%Y \\\"$$x\\\" \\\\"
+        );
+    }
+}
@@ -0,0 +1,7 @@
+/// A value translated from another syntax and what was lost in the translation
+#[derive(Debug, PartialEq, Clone)]
+pub struct Translation<T> {
+    pub value: T,
+    /// Human readable notes on parts which could not be kept as they were
+    pub warnings: Vec<String>,
+}
@@ -0,0 +1,463 @@
+//! Reports of invalid entries in crontab files for CI
+use crate::crontab::{normalize, Crontab};
+use crate::expression::parse_block;
+use crate::output::escape_json;
+use std::str::FromStr;
+
+/// Names of the formats accepted by `ReportFormat::from_str`
+pub const REPORT_FORMATS: [&str; 4] = ["text", "github", "junit", "json"];
+
+/// Ranges of the five fields, the same as `Expression::new`
+const FIELD_RANGES: [(u32, u32); 5] = [(0, 59), (0, 23), (1, 31), (1, 12), (0, 7)];
+
+/// Names of the five fields in the reasons of `Expression::never_fires_reason`
+const FIELD_NAMES: [&str; 5] = ["minute", "hour", "date", "month", "day"];
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Problem {
+    /// 1-based line number, or 0 for the whole file
+    pub line: usize,
+    /// 1-based column of the field in characters, or 0 for the whole file
+    pub column: usize,
+    /// Message in a single line
+    pub message: String,
+}
+
+/// Result of validating a file
+#[derive(Debug, PartialEq, Clone)]
+pub struct FileReport {
+    pub path: String,
+    /// Lines of entries checked, valid, invalid or unsupported
+    pub lines: Vec<usize>,
+    pub problems: Vec<Problem>,
+    /// Lines valid for cron but not checked, like `@reboot`, which are not problems
+    pub unsupported: Vec<Problem>,
+    /// Whether the file cannot be read, with the reason as the problem on line 0
+    pub unreadable: bool,
+}
+
+impl FileReport {
+    /// Returns the report of entries which cannot be parsed or never fire
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cron_gate::validate::FileReport;
+    ///
+    /// let r = FileReport::check("crontab", "# jobs\n0 9 * * * a\n0 9 60 * * b\n0 0 30 2 * c\n@reboot d\n");
+    /// assert_eq!(r.lines, vec![2, 3, 4, 5]);
+    /// assert_eq!((r.problems[0].line, r.problems[0].column), (3, 5));
+    /// assert_eq!(r.problems[1].message, "Never fires: date '30' does not exist in February (29 days at most)");
+    /// assert_eq!((r.problems[1].line, r.problems[1].column), (4, 5));
+    /// assert_eq!(r.unsupported[0].line, 5);
+    /// ```
+    pub fn check(path: &str, text: &str) -> FileReport {
+        let crontab = Crontab::parse(text);
+        let raw_lines: Vec<&str> = text.lines().collect();
+
+        let mut problems: Vec<Problem> = crontab
+            .errors
+            .iter()
+            .map(|(line, e)| Problem {
+                line: *line,
+                column: error_column(raw_lines[line - 1]),
+                message: e.replace('\n', ": "),
+            })
+            .collect();
+        problems.extend(crontab.entries.iter().filter_map(|entry| {
+            entry.expression.never_fires_reason().map(|reason| Problem {
+                line: entry.line,
+                column: reason_column(raw_lines[entry.line - 1], &reason),
+                message: reason,
+            })
+        }));
+        problems.sort_by_key(|p| p.line);
+        let unsupported: Vec<Problem> = crontab
+            .unsupported
+            .iter()
+            .map(|(line, message)| Problem {
+                line: *line,
+                column: field_columns(raw_lines[line - 1])[0],
+                message: message.clone(),
+            })
+            .collect();
+
+        let mut lines: Vec<usize> = crontab.entries.iter().map(|e| e.line).collect();
+        lines.extend(crontab.errors.iter().map(|(line, _)| *line));
+        lines.extend(crontab.unsupported.iter().map(|(line, _)| *line));
+        lines.sort_unstable();
+
+        FileReport {
+            path: path.to_string(),
+            lines,
+            problems,
+            unsupported,
+            unreadable: false,
+        }
+    }
+
+    /// Returns the report of a file which cannot be read, having the message as a problem
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cron_gate::validate::FileReport;
+    ///
+    /// let r = FileReport::unreadable("missing.txt", "Cannot read missing.txt: No such file");
+    /// assert!(r.unreadable);
+    /// assert!(r.lines.is_empty());
+    /// assert_eq!((r.problems[0].line, r.problems[0].column), (0, 0));
+    /// ```
+    pub fn unreadable(path: &str, message: &str) -> FileReport {
+        FileReport {
+            path: path.to_string(),
+            lines: vec![],
+            problems: vec![Problem {
+                line: 0,
+                column: 0,
+                message: message.to_string(),
+            }],
+            unsupported: vec![],
+            unreadable: true,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ReportFormat {
+    /// `path:line:column: message` per line
+    Text,
+    /// Workflow commands of GitHub Actions, shown inline in pull requests
+    Github,
+    /// JUnit XML having a test case per entry
+    Junit,
+    /// An array of objects
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ReportFormat, String> {
+        match s {
+            "text" => Ok(ReportFormat::Text),
+            "github" => Ok(ReportFormat::Github),
+            "junit" => Ok(ReportFormat::Junit),
+            "json" => Ok(ReportFormat::Json),
+            _ => Err(format!(
+                "Unknown format '{}': should be one of {}",
+                s,
+                REPORT_FORMATS.join(", ")
+            )),
+        }
+    }
+}
+
+/// Returns the problems and the unsupported lines of the reports rendered in the format
+///
+/// Unsupported lines are rendered as warnings.
+///
+/// # Examples
+///
+/// ```
+/// use cron_gate::validate::{render, FileReport, ReportFormat};
+///
+/// let reports = [FileReport::check("crontab", "0 25 * * * a\n")];
+/// assert_eq!(
+///     render(&reports, ReportFormat::Text),
+///     "crontab:1:3: Error on hour: '25': Invalid expression on '25': Invalid range '25': should be in 0 to 23\n"
+/// );
+/// assert!(render(&reports, ReportFormat::Github).starts_with("::error file=crontab,line=1,col=3::"));
+/// ```
+pub fn render(reports: &[FileReport], format: ReportFormat) -> String {
+    let findings = reports.iter().flat_map(|r| {
+        let mut findings: Vec<(&str, &Problem, bool)> = r
+            .problems
+            .iter()
+            .map(|p| (r.path.as_str(), p, true))
+            .chain(r.unsupported.iter().map(|p| (r.path.as_str(), p, false)))
+            .collect();
+        findings.sort_by_key(|(_, p, _)| p.line);
+        findings
+    });
+    match format {
+        ReportFormat::Text => findings
+            .map(|(path, p, error)| {
+                let level = if error { "" } else { "warning: " };
+                if p.line == 0 {
+                    format!("{}: {}{}\n", path, level, p.message)
+                } else {
+                    format!("{}:{}:{}: {}{}\n", path, p.line, p.column, level, p.message)
+                }
+            })
+            .collect(),
+        ReportFormat::Github => findings
+            .map(|(path, p, error)| {
+                let location = if p.line == 0 {
+                    String::new()
+                } else {
+                    format!(",line={},col={}", p.line, p.column)
+                };
+                format!(
+                    "::{} file={}{}::{}\n",
+                    if error { "error" } else { "warning" },
+                    escape_github_property(path),
+                    location,
+                    escape_github(&p.message)
+                )
+            })
+            .collect(),
+        ReportFormat::Json => {
+            let objects: Vec<String> = findings
+                .map(|(path, p, error)| {
+                    format!(
+                        "{{\"path\":\"{}\",\"line\":{},\"column\":{},\"level\":\"{}\",\"message\":\"{}\"}}",
+                        escape_json(path),
+                        p.line,
+                        p.column,
+                        if error { "error" } else { "warning" },
+                        escape_json(&p.message)
+                    )
+                })
+                .collect();
+            format!("[{}]\n", objects.join(","))
+        }
+        ReportFormat::Junit => junit(reports),
+    }
+}
+
+fn junit(reports: &[FileReport]) -> String {
+    let tests: usize = reports.iter().map(testcases).sum();
+    let failures: usize = reports.iter().map(|r| r.problems.len()).sum();
+    let skipped: usize = reports.iter().map(|r| r.unsupported.len()).sum();
+    let mut ret = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <testsuites name=\"cron_gate\" tests=\"{}\" failures=\"{}\" skipped=\"{}\">\n",
+        tests, failures, skipped
+    );
+    for report in reports {
+        let path = escape_xml(&report.path);
+        ret.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\">\n",
+            path,
+            testcases(report),
+            report.problems.len(),
+            report.unsupported.len()
+        ));
+        if report.unreadable {
+            let problems: Vec<Problem> = report
+                .problems
+                .iter()
+                .filter(|p| p.line == 0)
+                .cloned()
+                .collect();
+            ret.push_str(&testcase(&path, &problems, &[]));
+        }
+        for line in report.lines.iter() {
+            let on_line = |problems: &[Problem]| -> Vec<Problem> {
+                problems
+                    .iter()
+                    .filter(|p| p.line == *line)
+                    .cloned()
+                    .collect()
+            };
+            ret.push_str(&testcase(
+                &format!("{}:{}", path, line),
+                &on_line(&report.problems),
+                &on_line(&report.unsupported),
+            ));
+        }
+        ret.push_str("  </testsuite>\n");
+    }
+    ret.push_str("</testsuites>\n");
+    ret
+}
+
+/// Returns the number of test cases: the lines, and the file if it cannot be read
+fn testcases(report: &FileReport) -> usize {
+    report.lines.len() + report.unreadable as usize
+}
+
+/// Returns a test case failing by the problems or skipped by the unsupported lines
+fn testcase(name: &str, problems: &[Problem], unsupported: &[Problem]) -> String {
+    if problems.is_empty() && unsupported.is_empty() {
+        return format!("    <testcase name=\"{}\"/>\n", name);
+    }
+    let mut ret = format!("    <testcase name=\"{}\">\n", name);
+    for p in problems {
+        ret.push_str(&format!(
+            "      <failure message=\"{}\">{}</failure>\n",
+            escape_xml(&p.message),
+            if p.line == 0 {
+                name.to_string()
+            } else {
+                format!("{}:{}", name, p.column)
+            }
+        ));
+    }
+    for p in unsupported {
+        ret.push_str(&format!(
+            "      <skipped message=\"{}\"/>\n",
+            escape_xml(&p.message)
+        ));
+    }
+    ret.push_str("    </testcase>\n");
+    ret
+}
+
+/// Returns the column of the first field which cannot be parsed,
+/// or of the line if the fields are missing
+fn error_column(raw: &str) -> usize {
+    let columns = field_columns(raw);
+    let normalized = normalize(raw);
+    let fields: Vec<&str> = normalized.split_whitespace().collect();
+    if fields.len() < 5 {
+        return columns[0];
+    }
+    FIELD_RANGES
+        .iter()
+        .zip(fields.iter())
+        .position(|((min, max), field)| parse_block(field, *min, *max).is_err())
+        .map(|i| columns[i])
+        .unwrap_or(columns[0])
+}
+
+/// Returns the column of the field named by the reason why the entry never fires
+fn reason_column(raw: &str, reason: &str) -> usize {
+    let columns = field_columns(raw);
+    FIELD_NAMES
+        .iter()
+        .position(|name| reason.starts_with(&format!("Never fires: {} '", name)))
+        .and_then(|i| columns.get(i))
+        .copied()
+        .unwrap_or(columns[0])
+}
+
+/// Returns 1-based columns where the whitespace-separated fields start
+fn field_columns(raw: &str) -> Vec<usize> {
+    let mut ret = vec![];
+    let mut previous = ' ';
+    for (i, c) in raw.chars().enumerate() {
+        if !c.is_whitespace() && previous.is_whitespace() {
+            ret.push(i + 1);
+        }
+        previous = c;
+    }
+    if ret.is_empty() {
+        ret.push(1);
+    }
+    ret
+}
+
+fn escape_github(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_github_property(text: &str) -> String {
+    escape_github(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::Expression;
+
+    #[test]
+    fn test_check() {
+        let r = FileReport::check(
+            "a",
+            "\n  0 9 * * * ok\nSHELL=/bin/sh\n  0 9 * JAN-X * ng\nnope\n",
+        );
+        assert_eq!(r.lines, vec![2, 4, 5]);
+        assert_eq!(r.problems.len(), 2);
+        assert_eq!((r.problems[0].line, r.problems[0].column), (4, 9));
+        assert_eq!((r.problems[1].line, r.problems[1].column), (5, 1));
+        assert_eq!(r.problems[1].message, "Invalid expression: nope");
+
+        let r = FileReport::check(
+            "a",
+            "@daily a\n0 9 * jan mon-fri b\n0 9 * jan 8 c\n@reboot d\n",
+        );
+        assert_eq!(r.lines, vec![1, 2, 3, 4]);
+        assert_eq!(r.problems.len(), 1);
+        assert_eq!((r.problems[0].line, r.problems[0].column), (3, 11));
+        assert_eq!(r.unsupported.len(), 1);
+    }
+
+    #[test]
+    fn test_reason_column() {
+        let reason = |raw| {
+            let e = Expression::new(raw).unwrap();
+            reason_column(raw, &e.never_fires_reason().unwrap())
+        };
+        assert_eq!(reason("0 0  31 4 * a"), 6);
+        assert_eq!(reason("0 0 30 2 * a"), 5);
+        let day = "Never fires: day '8' matches no value";
+        assert_eq!(reason_column("0 0 * * 8 a", day), 9);
+        assert_eq!(reason_column("@yearly a", day), 1);
+        assert_eq!(reason_column("0 0 * * * a", "Unknown"), 1);
+    }
+
+    #[test]
+    fn test_field_columns() {
+        assert_eq!(field_columns("0 9  * * *"), vec![1, 3, 6, 8, 10]);
+        assert_eq!(field_columns("\t 0 9"), vec![3, 5]);
+        assert_eq!(field_columns(""), vec![1]);
+    }
+
+    #[test]
+    fn test_render() {
+        let reports = [
+            FileReport::check("a.txt", "0 9 * * * ok\n"),
+            FileReport::check("b.txt", "0 9 * * * ok\n0 0 30 2 * \"ng\"\n"),
+        ];
+        assert_eq!(
+            render(&reports, ReportFormat::Json),
+            "[{\"path\":\"b.txt\",\"line\":2,\"column\":5,\"level\":\"error\",\"message\":\"Never fires: date '30' does not exist in February (29 days at most)\"}]\n"
+        );
+        let junit = render(&reports, ReportFormat::Junit);
+        assert!(junit
+            .contains("<testsuites name=\"cron_gate\" tests=\"3\" failures=\"1\" skipped=\"0\">"));
+        assert!(junit.contains("    <testcase name=\"a.txt:1\"/>\n"));
+        assert!(junit.contains("<failure message=\"Never fires: date &apos;30&apos;"));
+        assert_eq!(render(&reports[..1], ReportFormat::Github), "");
+    }
+
+    #[test]
+    fn test_render_unsupported_and_unreadable() {
+        let reports = [
+            FileReport::check("a.txt", "@reboot up.sh\n"),
+            FileReport::unreadable("b.txt", "Cannot read b.txt"),
+        ];
+        assert_eq!(
+            render(&reports, ReportFormat::Text),
+            "a.txt:1:1: warning: '@reboot' runs at startup, not at a datetime\nb.txt: Cannot read b.txt\n"
+        );
+        assert_eq!(
+            render(&reports, ReportFormat::Github),
+            "::warning file=a.txt,line=1,col=1::'@reboot' runs at startup, not at a datetime\n::error file=b.txt::Cannot read b.txt\n"
+        );
+        let junit = render(&reports, ReportFormat::Junit);
+        assert!(junit.contains("tests=\"2\" failures=\"1\" skipped=\"1\""));
+        assert!(junit.contains("<skipped message=\"&apos;@reboot&apos; runs"));
+        assert!(junit.contains(
+            "    <testcase name=\"b.txt\">\n      <failure message=\"Cannot read b.txt\">b.txt</failure>"
+        ));
+    }
+
+    #[test]
+    fn test_escape_github() {
+        assert_eq!(escape_github("50%\na"), "50%25%0Aa");
+        assert_eq!(escape_github_property("a:b,c"), "a%3Ab%2Cc");
+    }
+}
@@ -0,0 +1,330 @@
+//! Structured API for JavaScript
+//!
+//! `Expression` is exported as a class whose methods return arrays of `Date`s
+//! and objects, and throw `Error`s instead of returning messages.
+//! Datetimes are evaluated in the local time of the JavaScript runtime.
+//!
+//! ```js
+//! import { Expression } from 'cron_gate'
+//!
+//! try {
+//!   const e = new Expression('0 9 * * 1-5 backup')
+//!   e.next(3, new Date(2019, 4, 28)) // [Date, Date, Date]
+//!   e.describe().hour // [9]
+//! } catch (error) {
+//!   console.error(error.message)
+//! }
+//! ```
+
+use crate::clock::Clock;
+use crate::compact::CompactExpression;
+use crate::expression::{days_in_month, Expression};
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
+use js_sys::{Array, Date, Object, Reflect};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TYPES: &'static str = r#"
+export interface Description {
+  /** The five fields without the command */
+  schedule: string;
+  command: string;
+  minute: number[];
+  hour: number[];
+  date: number[];
+  month: number[];
+  /** 0 and 7 are Sunday */
+  day: number[];
+  /** Why the expression never fires, or undefined if it fires */
+  neverFires?: string;
+  /** Each field as written and the values it expands to */
+  fields: Field[];
+}
+
+export interface Field {
+  name: "minute" | "hour" | "date" | "month" | "day";
+  text: string;
+  values: number[];
+}
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "Date[]")]
+    pub type DateArray;
+
+    #[wasm_bindgen(typescript_type = "Description")]
+    pub type Description;
+
+    #[wasm_bindgen(typescript_type = "number[]")]
+    pub type NumberArray;
+}
+
+/// The clock of the JavaScript runtime, since `Local::now` is not implemented on wasm32
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsClock;
+
+impl Clock for JsClock {
+    /// Returns the local time of the runtime
+    fn now(&self) -> DateTime<Local> {
+        let now = to_naive(&Date::new_0()).expect("The current time is a valid Date");
+        // `Local` is UTC on wasm32, so this keeps the fields of the local time of the runtime
+        Local.from_utc_datetime(&now)
+    }
+
+    /// Returns at once since JavaScript cannot block
+    fn sleep_until(&self, _: DateTime<Local>) {}
+}
+
+#[wasm_bindgen(js_name = Expression)]
+pub struct JsExpression {
+    expression: Expression,
+}
+
+#[wasm_bindgen(js_class = Expression)]
+impl JsExpression {
+    /// Throws an `Error` if the expression is invalid
+    #[wasm_bindgen(constructor)]
+    pub fn new(text: &str) -> Result<JsExpression, JsValue> {
+        Expression::new(text)
+            .map(|expression| JsExpression { expression })
+            .map_err(error)
+    }
+
+    /// Returns the next `n` datetimes from the minute of `after` (now if omitted)
+    ///
+    /// Throws an `Error` if the expression never fires or `after` is invalid.
+    pub fn next(&self, n: usize, after: Option<Date>) -> Result<DateArray, JsValue> {
+        self.check()?;
+        let after = match after {
+            Some(after) => to_naive(&after).map_err(error)?,
+            None => JsClock.now().naive_local(),
+        };
+        Ok(to_dates(&next_naive(&self.expression, after, n)))
+    }
+
+    /// Returns the previous `n` datetimes before the minute of `before` (now if omitted), the latest first
+    ///
+    /// Throws an `Error` if the expression never fires or `before` is invalid.
+    pub fn prev(&self, n: usize, before: Option<Date>) -> Result<DateArray, JsValue> {
+        self.check()?;
+        let before = match before {
+            Some(before) => to_naive(&before).map_err(error)?,
+            None => JsClock.now().naive_local(),
+        };
+        Ok(to_dates(&prev_naive(&self.expression, before, n)))
+    }
+
+    /// Returns true if the minute of the date matches the expression
+    ///
+    /// Throws an `Error` if the date is invalid.
+    pub fn matches(&self, date: &Date) -> Result<bool, JsValue> {
+        let date = to_naive(date).map_err(error)?;
+        Ok(CompactExpression::from(&self.expression).matches(date))
+    }
+
+    /// Returns the fields expanded into numbers
+    pub fn describe(&self) -> Description {
+        let e = &self.expression;
+        let object = Object::new();
+        set(&object, "schedule", &e.schedule().into());
+        set(&object, "command", &e.command.clone().into());
+        set(&object, "minute", &numbers(&e.minute_vec));
+        set(&object, "hour", &numbers(&e.hour_vec));
+        set(&object, "date", &numbers(&e.date_vec));
+        set(&object, "month", &numbers(&e.month_vec));
+        set(&object, "day", &numbers(&e.day_vec));
+        if let Some(reason) = e.never_fires_reason() {
+            set(&object, "neverFires", &reason.into());
+        }
+        let fields: Array = [
+            ("minute", &e.minute, &e.minute_vec),
+            ("hour", &e.hour, &e.hour_vec),
+            ("date", &e.date, &e.date_vec),
+            ("month", &e.month, &e.month_vec),
+            ("day", &e.day, &e.day_vec),
+        ]
+        .iter()
+        .map(|(name, text, values)| {
+            let field = Object::new();
+            set(&field, "name", &(*name).into());
+            set(&field, "text", &text.as_str().into());
+            set(&field, "values", &numbers(values));
+            JsValue::from(field)
+        })
+        .collect();
+        set(&object, "fields", &fields);
+        object.unchecked_into()
+    }
+
+    /// Returns dates of the month (1 to 12) on which the expression fires
+    #[wasm_bindgen(js_name = firingDays)]
+    pub fn firing_days(&self, year: i32, month: u32) -> Result<NumberArray, JsValue> {
+        firing_days(&self.expression, year, month)
+            .map(|days| numbers(&days).unchecked_into())
+            .map_err(error)
+    }
+
+    #[wasm_bindgen(js_name = toString)]
+    pub fn display(&self) -> String {
+        self.expression.to_string()
+    }
+
+    fn check(&self) -> Result<(), JsValue> {
+        match self.expression.never_fires_reason() {
+            Some(reason) => Err(error(reason)),
+            None => Ok(()),
+        }
+    }
+}
+
+// Utc carries local times of JavaScript as they are, so that `Date` applies
+// the time zone and its DST when converting them back.
+
+fn next_naive(e: &Expression, after: NaiveDateTime, n: usize) -> Vec<NaiveDateTime> {
+    e.earler_excuting_datetimes(Utc.from_utc_datetime(&after), n)
+        .iter()
+        .map(|d| d.naive_utc())
+        .collect()
+}
+
+fn prev_naive(e: &Expression, before: NaiveDateTime, n: usize) -> Vec<NaiveDateTime> {
+    e.previous_datetimes(Utc.from_utc_datetime(&before), n)
+        .iter()
+        .map(|d| d.naive_utc())
+        .collect()
+}
+
+fn firing_days(e: &Expression, year: i32, month: u32) -> Result<Vec<u32>, String> {
+    if !(1..=12).contains(&month) {
+        return Err(format!("Invalid month: {}", month));
+    }
+    if NaiveDate::from_ymd_opt(year, month, 1).is_none() {
+        return Err(format!("Invalid year: {}", year));
+    }
+    let compact = CompactExpression::from(e);
+    Ok((1..=days_in_month(year, month))
+        .filter(|d| compact.fires_on(NaiveDate::from_ymd(year, month, *d)))
+        .collect())
+}
+
+/// Returns the local time of the `Date`, or an error if it is `Invalid Date`
+/// or out of the range of chrono
+fn to_naive(date: &Date) -> Result<NaiveDateTime, String> {
+    if date.get_time().is_nan() {
+        return Err("Invalid Date".to_string());
+    }
+    naive_of(
+        date.get_full_year() as i32,
+        date.get_month() + 1,
+        date.get_date(),
+        (date.get_hours(), date.get_minutes(), date.get_seconds()),
+    )
+}
+
+fn naive_of(
+    year: i32,
+    month: u32,
+    day: u32,
+    (hour, minute, second): (u32, u32, u32),
+) -> Result<NaiveDateTime, String> {
+    NaiveDate::from_ymd_opt(year, month, day)
+        .and_then(|d| d.and_hms_opt(hour, minute, second))
+        .ok_or_else(|| {
+            format!(
+                "Date out of range: {}-{:02}-{:02} {:02}:{:02}:{:02}",
+                year, month, day, hour, minute, second
+            )
+        })
+}
+
+fn to_dates(datetimes: &[NaiveDateTime]) -> DateArray {
+    datetimes
+        .iter()
+        .map(|d| {
+            Date::new_with_year_month_day_hr_min(
+                d.year() as u32,
+                d.month() as i32 - 1,
+                d.day() as i32,
+                d.hour() as i32,
+                d.minute() as i32,
+            )
+        })
+        .collect::<Array>()
+        .unchecked_into()
+}
+
+fn numbers(values: &[u32]) -> JsValue {
+    values
+        .iter()
+        .map(|v| JsValue::from(*v))
+        .collect::<Array>()
+        .into()
+}
+
+fn set(object: &Object, key: &str, value: &JsValue) {
+    // Setting a property of a plain object never fails
+    let _ = Reflect::set(object, &key.into(), value);
+}
+
+fn error(message: String) -> JsValue {
+    js_sys::Error::new(&message).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_naive() {
+        let e = Expression::new("0 9 * * 1-5 command").unwrap();
+        let after = NaiveDate::from_ymd(2019, 5, 31).and_hms(9, 0, 30);
+        assert_eq!(
+            next_naive(&e, after, 2),
+            [
+                NaiveDate::from_ymd(2019, 5, 31).and_hms(9, 0, 0),
+                NaiveDate::from_ymd(2019, 6, 3).and_hms(9, 0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_firing_days() {
+        let e = Expression::new("0 9 * * 1-5 command").unwrap();
+        assert_eq!(
+            firing_days(&e, 2019, 6).unwrap(),
+            [3, 4, 5, 6, 7, 10, 11, 12, 13, 14, 17, 18, 19, 20, 21, 24, 25, 26, 27, 28]
+        );
+        let e = Expression::new("0 9 29 2 * command").unwrap();
+        assert!(firing_days(&e, 2019, 2).unwrap().is_empty());
+        assert_eq!(firing_days(&e, 2020, 2).unwrap(), [29]);
+        assert!(firing_days(&e, 2020, 13).is_err());
+        assert!(firing_days(&e, 275760, 2).is_err());
+    }
+
+    #[test]
+    fn test_naive_of() {
+        assert_eq!(
+            naive_of(2019, 5, 28, (9, 30, 15)),
+            Ok(NaiveDate::from_ymd(2019, 5, 28).and_hms(9, 30, 15))
+        );
+        // Fields of `Invalid Date` are NaN, which become 0
+        assert!(naive_of(0, 1, 0, (0, 0, 0)).is_err());
+        // The last year of `Date` is after the one of chrono
+        assert!(naive_of(275760, 9, 13, (0, 0, 0)).is_err());
+    }
+
+    #[test]
+    fn test_prev_naive() {
+        let e = Expression::new("0 9 * * 1-5 command").unwrap();
+        let before = NaiveDate::from_ymd(2019, 6, 3).and_hms(9, 0, 0);
+        assert_eq!(
+            prev_naive(&e, before, 2),
+            [
+                NaiveDate::from_ymd(2019, 5, 31).and_hms(9, 0, 0),
+                NaiveDate::from_ymd(2019, 5, 30).and_hms(9, 0, 0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,118 @@
+//! Exit codes and output of the binary, with the clock stopped by `CRON_GATE_NOW`
+#![cfg(feature = "bin")]
+
+use std::process::{Command, Output};
+
+fn cron_gate(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_cron_gate"))
+        .args(args)
+        .env("CRON_GATE_NOW", "2019/05/28 09:30")
+        .output()
+        .unwrap()
+}
+
+fn status(args: &[&str]) -> i32 {
+    cron_gate(args).status.code().unwrap()
+}
+
+fn stdout(args: &[&str]) -> String {
+    let output = cron_gate(args);
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Writes a file in the temporary directory unique to the test
+fn temp_file(name: &str, text: &str) -> String {
+    let path = std::env::temp_dir().join(format!("cron_gate_{}_{}", std::process::id(), name));
+    std::fs::write(&path, text).unwrap();
+    path.to_string_lossy().to_string()
+}
+
+#[test]
+fn test_success() {
+    assert_eq!(
+        stdout(&["0 9,12 * * * backup", "-n", "2"]),
+        "2019/05/28 12:00 backup\n2019/05/29 09:00 backup\n"
+    );
+    assert_eq!(status(&["check", "0 9 * * *"]), 0);
+}
+
+#[test]
+fn test_shared_options() {
+    let jenkins = [
+        "--dialect",
+        "jenkins",
+        "--seed",
+        "folder/nightly",
+        "H 9 * * *",
+    ];
+    assert_eq!(
+        stdout(&[&["check"], &jenkins[..]].concat()),
+        "OK: 56 9 * * *\n"
+    );
+    assert_eq!(
+        stdout(&[&["next"], &jenkins[..], &["-n", "1"]].concat()),
+        "2019/05/28 09:56 [command]\n"
+    );
+    assert_eq!(
+        stdout(&["describe", "--seed", "7", "0~30 9 * * *"]),
+        stdout(&["describe", "2 9 * * *"])
+    );
+}
+
+#[test]
+fn test_explain_seed() {
+    let next = stdout(&["next", "--seed", "7", "0~30 9 * * *", "-n", "1"]);
+    let minute: u32 = next[14..16].parse().unwrap();
+    let explained = stdout(&["explain", "--seed", "7", "0~30 9 * * *"]);
+    let row: Vec<&str> = explained
+        .lines()
+        .find(|l| l.starts_with("minute"))
+        .unwrap()
+        .split_whitespace()
+        .collect();
+    assert_eq!(row[2], minute.to_string());
+    assert!(explained.contains(&format!("'0~30' is {} chosen by the seed", minute)));
+}
+
+#[test]
+fn test_findings() {
+    assert_eq!(status(&["lint", "* 9 * * *"]), 1);
+    let path = temp_file("findings.txt", "0 9 * * * a\n61 * * * * b\n");
+    assert_eq!(status(&["validate", &path]), 1);
+}
+
+#[test]
+fn test_usage_error() {
+    assert_eq!(status(&["0 9 * * *", "-n", "0"]), 2);
+    assert_eq!(status(&["0 9 * * *", "--after", "next week"]), 2);
+    assert_eq!(status(&["next", "0 9 * * *", "--unknown"]), 2);
+}
+
+#[test]
+fn test_parse_error() {
+    assert_eq!(status(&["61 * * * *"]), 3);
+    assert_eq!(status(&["explain", "0 9 * *"]), 3);
+    assert_eq!(status(&["--from-rrule", "FREQ=SOMETIMES"]), 3);
+}
+
+#[test]
+fn test_never_fires() {
+    assert_eq!(status(&["0 0 30 2 *"]), 4);
+    assert_eq!(status(&["prev", "0 0 31 4 *"]), 4);
+}
+
+#[test]
+fn test_unreadable_file() {
+    assert_eq!(status(&["validate", "/nonexistent/crontab"]), 5);
+    assert_eq!(
+        status(&["--crontab", "/nonexistent/crontab", "-f", "ics"]),
+        5
+    );
+
+    // The other files are still reported
+    let path = temp_file("valid.txt", "0 9 * * * a\n");
+    let output = cron_gate(&["validate", "/nonexistent/crontab", &path]);
+    assert_eq!(output.status.code(), Some(5));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Cannot read /nonexistent/crontab"));
+}
@@ -1,31 +1,188 @@
-import * as cron_gate from '../pkg/cron_gate'
+import { Expression } from '../pkg/cron_gate'
+
+const WEEKDAYS = ['Sun', 'Mon', 'Tue', 'Wed', 'Thu', 'Fri', 'Sat']
 
 const $form = document.getElementById('form')
 const $input = document.getElementById('input')
 const $after = document.getElementById('after')
 const $number = document.getElementById('number')
 const $output = document.getElementById('output')
+const $error = document.getElementById('error')
+const $fields = document.querySelector('#fields tbody')
+const $calendar = document.getElementById('calendar')
+const $title = document.getElementById('title')
+const $view = document.getElementById('view')
+
+// The first day shown in the calendar
+let cursor = startOfMonth(new Date())
 
-$after.value = getNow()
+$after.value = formatDate(new Date())
 
 $form.addEventListener('submit', e => {
   e.preventDefault()
-  exec()
+  render()
 })
+$form.addEventListener('input', render)
+$view.addEventListener('change', () => {
+  cursor = $view.value === 'week' ? startOfWeek(cursor) : startOfMonth(cursor)
+  render()
+})
+document.getElementById('prev').addEventListener('click', () => move(-1))
+document.getElementById('next').addEventListener('click', () => move(1))
+
+function move (direction) {
+  if ($view.value === 'week') {
+    cursor = new Date(cursor.getFullYear(), cursor.getMonth(), cursor.getDate() + 7 * direction)
+  } else {
+    cursor = new Date(cursor.getFullYear(), cursor.getMonth() + direction, 1)
+  }
+  render()
+}
+
+function render () {
+  let expression
+  try {
+    expression = new Expression($input.value)
+  } catch (e) {
+    showError(e.message)
+    return
+  }
+
+  try {
+    const description = expression.describe()
+    renderFields(description)
+    if ($view.value === 'week') {
+      renderWeek(expression, description)
+    } else {
+      renderMonth(expression, description)
+    }
+
+    const after = parseDate($after.value)
+    let number = parseInt($number.value)
+    if (!(number >= 1)) number = 20
+    $output.value = expression.next(number, after).map(formatDate).join('\n')
+    showError(description.neverFires || '')
+  } catch (e) {
+    $output.value = ''
+    showError(e.message)
+  } finally {
+    expression.free()
+  }
+}
+
+function renderFields (description) {
+  $fields.innerHTML = ''
+  description.fields.forEach(field => {
+    const $tr = document.createElement('tr')
+    ;[field.name, field.text, field.values.join(', ')].forEach(text => {
+      const $td = document.createElement('td')
+      $td.textContent = text
+      $tr.appendChild($td)
+    })
+    $fields.appendChild($tr)
+  })
+}
+
+function renderMonth (expression, description) {
+  const year = cursor.getFullYear()
+  const month = cursor.getMonth()
+  const days = expression.firingDays(year, month + 1)
+  const runs = description.hour.length * description.minute.length
+  $title.textContent = `${year}/${month + 1}`
+
+  $calendar.innerHTML = ''
+  $calendar.appendChild(header(WEEKDAYS))
+  let $tr = document.createElement('tr')
+  for (let i = 0; i < cursor.getDay(); i++) {
+    $tr.appendChild(document.createElement('td'))
+  }
+  const last = new Date(year, month + 1, 0).getDate()
+  for (let date = 1; date <= last; date++) {
+    const $td = document.createElement('td')
+    $td.textContent = date
+    if (days.includes(date)) {
+      $td.className = 'fires'
+      $td.title = `${runs} runs`
+    }
+    $tr.appendChild($td)
+    if ($tr.children.length === 7) {
+      $calendar.appendChild($tr)
+      $tr = document.createElement('tr')
+    }
+  }
+  if ($tr.children.length > 0) $calendar.appendChild($tr)
+}
+
+function renderWeek (expression, description) {
+  const dates = [...Array(7).keys()].map(i =>
+    new Date(cursor.getFullYear(), cursor.getMonth(), cursor.getDate() + i)
+  )
+  const firingDays = {}
+  const fires = date => {
+    const key = `${date.getFullYear()}/${date.getMonth() + 1}`
+    if (!firingDays[key]) {
+      firingDays[key] = expression.firingDays(date.getFullYear(), date.getMonth() + 1)
+    }
+    return firingDays[key].includes(date.getDate())
+  }
+  const minutes = description.minute.length > 4
+    ? `${description.minute.length} runs`
+    : description.minute.map(m => `:${pad(m)}`).join(' ')
+  $title.textContent = `${formatDate(dates[0]).slice(0, 10)} -`
+
+  $calendar.innerHTML = ''
+  $calendar.appendChild(header([''].concat(dates.map(d => `${WEEKDAYS[d.getDay()]} ${d.getDate()}`))))
+  for (let hour = 0; hour < 24; hour++) {
+    const $tr = document.createElement('tr')
+    const $th = document.createElement('th')
+    $th.textContent = `${pad(hour)}:00`
+    $tr.appendChild($th)
+    dates.forEach(date => {
+      const $td = document.createElement('td')
+      if (description.hour.includes(hour) && fires(date)) {
+        $td.className = 'fires'
+        $td.textContent = minutes
+      }
+      $tr.appendChild($td)
+    })
+    $calendar.appendChild($tr)
+  }
+}
+
+function header (names) {
+  const $tr = document.createElement('tr')
+  names.forEach(name => {
+    const $th = document.createElement('th')
+    $th.textContent = name
+    $tr.appendChild($th)
+  })
+  return $tr
+}
+
+function showError (message) {
+  $error.textContent = message
+}
+
+function parseDate (text) {
+  const m = text.trim().match(/^(\d+)\/(\d+)\/(\d+) (\d+):(\d+)$/)
+  if (!m) throw new Error(`${text} is an invalid format of 'after': yyyy/mm/dd HH:MM`)
+  return new Date(m[1], m[2] - 1, m[3], m[4], m[5])
+}
+
+function formatDate (date) {
+  return `${date.getFullYear()}/${pad(date.getMonth() + 1)}/${pad(date.getDate())} ${pad(date.getHours())}:${pad(date.getMinutes())}`
+}
+
+function pad (n) {
+  return `${n}`.padStart(2, '0')
+}
 
-function exec () {
-  const value = $input.value
-  const after = $after.value
-  let number = parseInt($number.value)
-  if (number < 1) number = 20
-  $number.value = number
-  const text = cron_gate.get_datetimes(value, after, number)
-  $output.value = text
+function startOfMonth (date) {
+  return new Date(date.getFullYear(), date.getMonth(), 1)
 }
 
-function getNow () {
-  const now = new Date()
-  return `${now.getFullYear()}/${now.getMonth()}/${now.getDate()} ${now.getHours()}:${now.getMinutes()}`
+function startOfWeek (date) {
+  return new Date(date.getFullYear(), date.getMonth(), date.getDate() - date.getDay())
 }
 
-exec()
+render()